@@ -0,0 +1,177 @@
+/*
+ * Command line arguments for the computer_mark_up binary
+ * computer_mark_up <file.cmu> [options]
+ */
+
+use crate::file_reader::{self, ReadErrorKind};
+use crate::render::color::Color;
+
+pub const USAGE: &str = "\
+usage: computer_mark_up <file.cmu> [options]
+
+options:
+    --width <pixels>        width of the window
+    --height <pixels>       height of the window
+    --title <text>          title of the window, defaults to the file name
+    --background <color>    clear color behind everything, ie (25,50,75) or 0.1,0.2,0.3
+    --png <path>            render once without a window and save it as a png
+    --software              with --png, draw on the cpu instead of the gpu
+    -h, --help              print this message";
+
+#[derive(Debug)]
+pub struct Arguments{
+    pub file: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub title: Option<String>,
+    pub background: Option<Color>,
+    // set when rendering straight to an image instead of a window
+    pub png: Option<String>,
+    // skip the gpu entirely when rendering to an image
+    pub software: bool,
+}
+
+// What main should do after looking at the arguments
+#[derive(Debug)]
+pub enum Command{
+    Open(Arguments),
+    Help,
+}
+
+fn expect_value(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String>{
+    args.next().ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn parse_pixels(flag: &str, value: String) -> Result<u32, String>{
+    match value.parse::<u32>(){
+        Ok(pixels) if pixels > 0 => Ok(pixels),
+        _ => Err(format!("{} needs to be a whole number above 0, got '{}'", flag, value)),
+    }
+}
+
+// args should not include the program name
+pub fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Command, String>{
+    let mut file = None;
+    let mut width = None;
+    let mut height = None;
+    let mut title = None;
+    let mut background = None;
+    let mut png = None;
+    let mut software = false;
+
+    while let Some(arg) = args.next(){
+        match arg.as_str(){
+            "-h" | "--help" => return Ok(Command::Help),
+            "--width" => width = Some(parse_pixels(&arg, expect_value(&arg, &mut args)?)?),
+            "--height" => height = Some(parse_pixels(&arg, expect_value(&arg, &mut args)?)?),
+            "--title" => title = Some(expect_value(&arg, &mut args)?),
+            "--background" => {
+                let value = expect_value(&arg, &mut args)?;
+                let color = file_reader::parse_color(&value).map_err(|e| match e.kind{
+                    ReadErrorKind::BadDefinition(message) => format!("--background: {}", message),
+                    other => format!("--background: {}", other),
+                })?;
+                background = Some(Color::new(color));
+            },
+            "--png" => png = Some(expect_value(&arg, &mut args)?),
+            "--software" => software = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            _ if file.is_some() => return Err(format!("only one file can be opened, got '{}' as well", arg)),
+            _ => file = Some(arg),
+        }
+    }
+
+    // there's only ever a gpu behind a window
+    if software && png.is_none(){
+        return Err(String::from("--software only works along with --png"));
+    }
+
+    match file{
+        Some(file) => Ok(Command::Open(Arguments{ file, width, height, title, background, png, software })),
+        None => Err(String::from("no .cmu file was given")),
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String>{
+        parse_arguments(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    fn open(args: &[&str]) -> Arguments{
+        match parse(args){
+            Ok(Command::Open(arguments)) => arguments,
+            other => panic!("expected a file to open, got {:?}", other),
+        }
+    }
+
+    fn error(args: &[&str]) -> String{
+        parse(args).expect_err("the arguments are wrong")
+    }
+
+    #[test]
+    fn every_option(){
+        let arguments = open(&[
+            "page.cmu", "--width", "640", "--height", "480", "--title", "Page",
+            "--background", "(255,0,0)", "--png", "out.png", "--software"
+        ]);
+        assert_eq!(arguments.file, "page.cmu");
+        assert_eq!((arguments.width, arguments.height), (Some(640), Some(480)));
+        assert_eq!(arguments.title.as_deref(), Some("Page"));
+        assert_eq!(arguments.background, Some(Color::new([1.0, 0.0, 0.0, 1.0])));
+        assert_eq!(arguments.png.as_deref(), Some("out.png"));
+        assert!(arguments.software);
+
+        // the file can go anywhere
+        let arguments = open(&["--width", "10", "page.cmu"]);
+        assert_eq!(arguments.file, "page.cmu");
+        assert_eq!(arguments.png, None);
+    }
+
+    #[test]
+    fn help_stops_reading_the_arguments(){
+        assert!(matches!(parse(&["-h"]), Ok(Command::Help)));
+        assert!(matches!(parse(&["page.cmu", "--help", "--nope"]), Ok(Command::Help)));
+        // anything before it still has to make sense
+        assert!(parse(&["--nope", "-h"]).is_err());
+    }
+
+    #[test]
+    fn bad_sizes(){
+        assert_eq!(error(&["page.cmu", "--width", "0"]), "--width needs to be a whole number above 0, got '0'");
+        assert!(error(&["page.cmu", "--width", "-3"]).contains("above 0"));
+        assert!(error(&["page.cmu", "--height", "1.5"]).contains("above 0"));
+        assert_eq!(error(&["page.cmu", "--height"]), "--height needs a value");
+    }
+
+    #[test]
+    fn bad_background(){
+        assert!(error(&["page.cmu", "--background", "(256,0,0)"]).starts_with("--background: "));
+        assert!(error(&["page.cmu", "--background", "red"]).starts_with("--background: "));
+        assert_eq!(error(&["page.cmu", "--background"]), "--background needs a value");
+        // the brackets can be left off
+        let arguments = open(&["page.cmu", "--background", "0.0,0.5,1.0"]);
+        assert_eq!(arguments.background, Some(Color::new([0.0, 0.5, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn needs_exactly_one_file(){
+        assert_eq!(error(&[]), "no .cmu file was given");
+        assert_eq!(error(&["--width", "10"]), "no .cmu file was given");
+        assert_eq!(error(&["a.cmu", "b.cmu"]), "only one file can be opened, got 'b.cmu' as well");
+    }
+
+    #[test]
+    fn unknown_options(){
+        assert_eq!(error(&["page.cmu", "--fullscreen"]), "unknown option '--fullscreen'");
+        assert_eq!(error(&["-x", "page.cmu"]), "unknown option '-x'");
+    }
+
+    #[test]
+    fn software_needs_png(){
+        assert_eq!(error(&["page.cmu", "--software"]), "--software only works along with --png");
+        assert!(open(&["page.cmu", "--software", "--png", "out.png"]).software);
+    }
+}
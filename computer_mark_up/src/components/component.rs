@@ -1,6 +1,7 @@
 use crate::{
     update_commands::UpdateCommand, 
-    render::{vertex::ComponentVertex, texture::Texture}, input_handler::InputHandler
+    render::{instance::QuadInstance, vertex::ComponentVertex}, input_handler::InputHandler,
+    collision
 };
 
 pub type Component = Box<dyn ComponentObject>;
@@ -10,44 +11,70 @@ pub type Component = Box<dyn ComponentObject>;
 pub trait ComponentObject{
     /*
      * on_init() 
-     *     Called during Component instantiation
+     *     Called once when the component is added to a scene
+     * set_hovered()
+     *     Called before update(), true while the mouse is over
+     *     the component and nothing drawn above it gets in the way
      * update()
-     *     Called each frame
+     *     Called whenever something happens, ie the mouse moving
+     * take_changed()
+     *     Called after update(), the scene is only drawn again
+     *     when a component says it looks different
      * pre_render()
-     *     Called between each screen render
-     * get_sprite()
-     *     Returns information needed for rendering
+     *     Called between each screen render, after update()
+     *     and before get_instances()
+     * get_instances() / get_vertices()
+     *     Returns information needed for rendering, renderers
+     *     use get_vertices() when there aren't any instances
+     * contains()
+     *     Whether a point on screen is over the component,
+     *     SceneState uses it to find what's under the mouse
+     * position() / set_position()
+     *     The top left corner on screen, SceneState moves
+     *     components with this when their parent moves
+     * set_size()
+     *     Called when the box a FLOAT component fills changes size,
+     *     ie the window was resized, most components just take it
+     * set_opacity()
+     *     How solid the component should be drawn, from 0 to 1
+     *     This already includes the opacity of everything it's inside
+     *     Components are faded one by one, never as a group
      */
 
     fn on_init(&mut self){}
 
-    fn update(&mut self, input: &InputHandler) -> UpdateCommand{ UpdateCommand::Void }
+    fn set_hovered(&mut self, _hovered: bool){}
 
-    fn pre_render(&mut self) -> &Texture;
+    fn update(&mut self, _input: &InputHandler) -> UpdateCommand{ UpdateCommand::Void }
 
-    fn get_vertices(&self) -> [ComponentVertex; 4];
-}
+    // True if it looks different since the last time this was asked,
+    // components that never change after they're made can leave this
+    fn take_changed(&mut self) -> bool{ false }
 
-// A component can have three build options:
-// -> Default Build
-// -> Structured Build
-// -> Both of them
-// A default build will always make the same component
-// with the same specifications
-// A structured build will take a custom parameters object 
-// and will use it to construct the object
-//
-// Either way, the component needs to implement
-// the build process on its own
-
-
-pub trait DefaultBuild{
-    fn build_default(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration) -> Self;
-}
+    fn pre_render(&mut self){}
 
-pub trait CustomBuildParameters{}
+    // One rectangle per thing drawn, most components only need one
+    fn get_instances(&self) -> Vec<QuadInstance>{ Vec::new() }
 
-pub trait CustomBuild{
-    fn buid_custom(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, parameters: impl CustomBuildParameters) -> Self;
-}
+    // For anything that isn't an axis aligned rectangle
+    // By default it's just the corners of the instances
+    fn get_vertices(&self) -> Vec<[ComponentVertex; 4]>{
+        self.get_instances().iter().map(QuadInstance::vertices).collect()
+    }
+
+    // By default anywhere on one of the instances counts
+    fn contains(&self, point: [f32; 2]) -> bool{
+        self.get_instances().iter().any(|instance| {
+            let [[left, top], [right, bottom]] = instance.rect;
+            collision::point_on_rect([left, top], right - left, bottom - top, point)
+        })
+    }
 
+    fn position(&self) -> [f32; 2];
+
+    fn set_position(&mut self, corner: [f32; 2]);
+
+    fn set_size(&mut self, _size: [f32; 2]){}
+
+    fn set_opacity(&mut self, opacity: f32);
+}
@@ -1,59 +1,80 @@
-use crate::render::vertex::ComponentVertex;
-use crate::input_handler::InputHandler;
+use crate::render::{color::Color, instance::QuadInstance};
 use crate::collision;
 
 use super::ComponentObject;
 
-// a component whose texture changes when it is hovered
+// a component whose color changes when it is hovered
 pub struct HoverComponent{
     top_left_corner: [f32; 2],
     width: f32,
     height: f32,
-    base_texture: u32,
-    hover_texture: u32,
-    current_texture: u32
+    base_color: Color,
+    hover_color: Color,
+    current_color: Color,
+    // current_color is different to what was last drawn
+    changed: bool,
+    // pixels, 0 for square corners
+    corner_radius: f32,
+    opacity: f32
 }
 
 impl HoverComponent{
-    pub fn new(tlc: [f32; 2], width: f32, height: f32, base: u32, hover: u32) -> Self{
+    pub fn new(tlc: [f32; 2], width: f32, height: f32, base: Color, hover: Color) -> Self{
         Self { 
             top_left_corner: tlc, 
             width, 
             height, 
-            base_texture: base, 
-            hover_texture: hover,
-            current_texture: 0u32
+            base_color: base, 
+            hover_color: hover,
+            current_color: base,
+            changed: false,
+            corner_radius: 0.0,
+            opacity: 1.0
         }
     }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self{
+        self.corner_radius = corner_radius;
+        self
+    }
 }
 
 impl ComponentObject for HoverComponent{
-    fn update(&mut self, input: &InputHandler) -> crate::update_commands::UpdateCommand {
-        // do the collision detection
-        let mcoords = input.get_mouse_pos();
-        let collided = collision::point_on_rect(
-            self.top_left_corner, 
-            self.width, 
-            self.height, 
-            mcoords
-        );
-        self.current_texture = self.base_texture * collided as u32 + self.hover_texture * !collided as u32;
-        crate::update_commands::UpdateCommand::Void
-    }
-
-    fn get_vertices(&self) -> [crate::render::vertex::ComponentVertex; 4] {
-        let trc = [self.top_left_corner[0] + self.width, self.top_left_corner[1]              ];
-        let blc = [self.top_left_corner[0],              self.top_left_corner[1] + self.height];
+    // hovered is worked out by the scene, so something
+    // drawn on top of this keeps it from being hovered
+    fn set_hovered(&mut self, hovered: bool) {
+        let color = if hovered { self.hover_color } else { self.base_color };
+        self.changed |= color != self.current_color;
+        self.current_color = color;
+    }
+
+    fn contains(&self, point: [f32; 2]) -> bool {
+        collision::point_on_rect(self.top_left_corner, self.width, self.height, point)
+    }
+
+    fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    fn get_instances(&self) -> Vec<QuadInstance> {
         let brc = [self.top_left_corner[0] + self.width, self.top_left_corner[1] + self.height];
-        [
-            ComponentVertex{ position: self.top_left_corner, texture_index: self.current_texture, texture_coords: [0.0, 1.0] },
-            ComponentVertex{ position: trc,                  texture_index: self.current_texture, texture_coords: [1.0, 0.0] },
-            ComponentVertex{ position: blc,                  texture_index: self.current_texture, texture_coords: [0.0, 0.0] },
-            ComponentVertex{ position: brc,                  texture_index: self.current_texture, texture_coords: [1.0, 0.0] }
-        ]
+        vec![QuadInstance::colored([self.top_left_corner, brc], self.current_color).rounded(self.corner_radius).faded(self.opacity)]
+    }
+
+    fn position(&self) -> [f32; 2] {
+        self.top_left_corner
+    }
+
+    fn set_position(&mut self, corner: [f32; 2]) {
+        self.top_left_corner = corner;
+    }
+
+    fn set_size(&mut self, [width, height]: [f32; 2]) {
+        self.width = width;
+        self.height = height;
     }
 
-    fn pre_render(&mut self) -> &crate::render::texture::Texture {
-        todo!()
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
     }
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+use crate::render::{assets::TextureHandle, instance::QuadInstance};
+use super::ComponentObject;
+
+/*
+ * A picture loaded from a file, drawn into a box
+ * The fit mode decides how the picture is scaled when
+ * the box isn't the same shape as it
+ */
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ImageFit{
+    // Squashed or stretched to fill the box exactly
+    #[default]
+    Stretch,
+    // As big as it can be while all of it stays in the box
+    Contain,
+    // As small as it can be while the box is covered, the rest is cut off
+    Cover,
+    // Its own size, centered and cut off if it's bigger than the box
+    None,
+}
+
+pub struct ImageComponent{
+    top_left_corner: [f32; 2],
+    width: f32,
+    height: f32,
+    fit: ImageFit,
+    texture: TextureHandle,
+    // pixels, 0 for square corners
+    corner_radius: f32,
+    opacity: f32
+}
+
+impl ImageComponent{
+    pub fn new(corner: [f32; 2], width: f32, height: f32, fit: ImageFit, texture: TextureHandle) -> Self{
+        Self{
+            top_left_corner: corner,
+            width,
+            height,
+            fit,
+            texture,
+            corner_radius: 0.0,
+            opacity: 1.0
+        }
+    }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self{
+        self.corner_radius = corner_radius;
+        self
+    }
+
+    // The part of the box the picture is drawn over,
+    // and the part of the picture that shows, both as [top left, bottom right]
+    fn placement(&self) -> ([[f32; 2]; 2], [[f32; 2]; 2]){
+        let size = [self.width, self.height];
+        // the picture's own size in pixels
+        let image_size = self.texture.size().map(|pixels| pixels as f32);
+        let fitted = [self.width / image_size[0], self.height / image_size[1]];
+        let scale = match self.fit{
+            ImageFit::Stretch => fitted,
+            ImageFit::Contain => [fitted[0].min(fitted[1]); 2],
+            ImageFit::Cover => [fitted[0].max(fitted[1]); 2],
+            ImageFit::None => [1.0, 1.0],
+        };
+
+        let mut rect = [[0.0; 2]; 2];
+        let mut texture_coords = [[0.0, 0.0], [1.0, 1.0]];
+        for axis in 0..2{
+            let drawn = image_size[axis] * scale[axis];
+            if drawn > size[axis]{
+                // centered, with the same amount cut off each side
+                let cut = (drawn - size[axis]) / 2.0 / drawn;
+                rect[1][axis] = size[axis];
+                texture_coords[0][axis] = cut;
+                texture_coords[1][axis] = 1.0 - cut;
+            } else {
+                rect[0][axis] = (size[axis] - drawn) / 2.0;
+                rect[1][axis] = rect[0][axis] + drawn;
+            }
+        }
+        (rect, texture_coords)
+    }
+}
+
+impl ComponentObject for ImageComponent{
+    fn get_instances(&self) -> Vec<QuadInstance> {
+        let (rect, texture_rect) = self.placement();
+        let rect = rect.map(|[x, y]| [self.top_left_corner[0] + x, self.top_left_corner[1] + y]);
+        vec![QuadInstance::textured(rect, self.texture.index(), texture_rect).rounded(self.corner_radius).faded(self.opacity)]
+    }
+
+    fn position(&self) -> [f32; 2] {
+        self.top_left_corner
+    }
+
+    fn set_position(&mut self, corner: [f32; 2]) {
+        self.top_left_corner = corner;
+    }
+
+    fn set_size(&mut self, [width, height]: [f32; 2]) {
+        self.width = width;
+        self.height = height;
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::render::assets::AssetManager;
+
+    // Where a 200x100 picture goes in a 100x80 box at (10, 20),
+    // and the part of it that shows
+    fn placed(fit: ImageFit, image_size: [u32; 2]) -> ([[f32; 2]; 2], [[f32; 2]; 2]){
+        let texture = AssetManager::new().add(0, image_size);
+        let image = ImageComponent::new([10.0, 20.0], 100.0, 80.0, fit, texture);
+        let instance = image.get_instances()[0];
+        (instance.rect, instance.texture_rect)
+    }
+
+    fn assert_close(actual: [[f32; 2]; 2], expected: [[f32; 2]; 2]){
+        let close = actual.iter().flatten()
+            .zip(expected.iter().flatten())
+            .all(|(a, e)| (a - e).abs() < 1e-5);
+        assert!(close, "{:?} isn't {:?}", actual, expected);
+    }
+
+    const WHOLE: [[f32; 2]; 2] = [[0.0, 0.0], [1.0, 1.0]];
+    const BOX: [[f32; 2]; 2] = [[10.0, 20.0], [110.0, 100.0]];
+
+    #[test]
+    fn stretch_fills_the_box(){
+        let (rect, texture_rect) = placed(ImageFit::Stretch, [200, 100]);
+        assert_close(rect, BOX);
+        assert_close(texture_rect, WHOLE);
+    }
+
+    #[test]
+    fn contain_leaves_bars_on_the_short_side(){
+        // half size, 100x50, centered top to bottom
+        let (rect, texture_rect) = placed(ImageFit::Contain, [200, 100]);
+        assert_close(rect, [[10.0, 35.0], [110.0, 85.0]]);
+        assert_close(texture_rect, WHOLE);
+    }
+
+    #[test]
+    fn cover_cuts_off_the_long_side(){
+        // 160x80, so 30 pixels are cut off each side
+        let (rect, texture_rect) = placed(ImageFit::Cover, [200, 100]);
+        assert_close(rect, BOX);
+        assert_close(texture_rect, [[0.1875, 0.0], [0.8125, 1.0]]);
+    }
+
+    #[test]
+    fn none_keeps_its_own_size(){
+        // bigger than the box both ways, so the middle of it shows
+        let (rect, texture_rect) = placed(ImageFit::None, [200, 100]);
+        assert_close(rect, BOX);
+        assert_close(texture_rect, [[0.25, 0.1], [0.75, 0.9]]);
+
+        // smaller, so it's centered with room all round
+        let (rect, texture_rect) = placed(ImageFit::None, [40, 20]);
+        assert_close(rect, [[40.0, 50.0], [80.0, 70.0]]);
+        assert_close(texture_rect, WHOLE);
+    }
+}
@@ -1,8 +1,13 @@
 mod component;
 mod hover;
+mod image;
 mod square;
 mod text;
+mod text_layout;
 
 pub use component::{Component, ComponentObject};
 pub use hover::HoverComponent;
-pub use square::SquareComponent;
\ No newline at end of file
+pub use image::{ImageComponent, ImageFit};
+pub use square::SquareComponent;
+pub use text::{TextComponent, TextStyle, DEFAULT_FONT_SIZE};
+pub use text_layout::{TextAlign, TextOverflow};
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::render::vertex::ComponentVertex;
+use crate::render::{color::Color, instance::QuadInstance};
 use super::ComponentObject;
 
 /*
@@ -8,33 +8,51 @@ use super::ComponentObject;
 pub struct SquareComponent{
     top_left_corner: [f32; 2],
     width: f32,
-    height: f32
+    height: f32,
+    color: Color,
+    // pixels, 0 for square corners
+    corner_radius: f32,
+    opacity: f32
 }
 
 impl SquareComponent{
-    pub fn new(corner: [f32; 2], width: f32, height: f32) -> Self{
+    pub fn new(corner: [f32; 2], width: f32, height: f32, color: Color) -> Self{
         Self { 
             top_left_corner: corner, 
             width, 
-            height 
+            height,
+            color,
+            corner_radius: 0.0,
+            opacity: 1.0
         }
     }
+
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self{
+        self.corner_radius = corner_radius;
+        self
+    }
 }
 
 impl ComponentObject for SquareComponent{
-    fn get_vertices(&self) -> [ComponentVertex; 4] {
-        let trc = [self.top_left_corner[0] + self.width, self.top_left_corner[1]              ];
-        let blc = [self.top_left_corner[0],              self.top_left_corner[1] + self.height];
+    fn get_instances(&self) -> Vec<QuadInstance> {
         let brc = [self.top_left_corner[0] + self.width, self.top_left_corner[1] + self.height];
-        [
-            ComponentVertex{ position: self.top_left_corner, texture_index: 0, texture_coords: [0.0, 1.0] },
-            ComponentVertex{ position: trc,                  texture_index: 0, texture_coords: [1.0, 0.0] },
-            ComponentVertex{ position: blc,                  texture_index: 0, texture_coords: [0.0, 0.0] },
-            ComponentVertex{ position: brc,                  texture_index: 0, texture_coords: [1.0, 0.0] }
-        ]
+        vec![QuadInstance::colored([self.top_left_corner, brc], self.color).rounded(self.corner_radius).faded(self.opacity)]
+    }
+
+    fn position(&self) -> [f32; 2] {
+        self.top_left_corner
+    }
+
+    fn set_position(&mut self, corner: [f32; 2]) {
+        self.top_left_corner = corner;
+    }
+
+    fn set_size(&mut self, [width, height]: [f32; 2]) {
+        self.width = width;
+        self.height = height;
     }
 
-    fn pre_render(&mut self) -> &crate::render::texture::Texture {
-        todo!()
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
     }
 }
\ No newline at end of file
@@ -0,0 +1,185 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::render::{
+    color::Color,
+    font::Font,
+    glyph_atlas::{AtlasGlyph, GlyphAtlas, GlyphKey},
+    instance::QuadInstance
+};
+
+use super::{text_layout::{layout, TextAlign, TextLayout, TextOverflow}, ComponentObject};
+
+/*
+ * A block of text drawn with a font
+ * Glyphs come from the scene's shared GlyphAtlas, and each one
+ * is drawn as a quad cut out of an atlas page
+ * They're looked up again in pre_render every frame, which keeps
+ * them from being evicted and picks up any that were
+ * Text wraps to the width it's given, see text_layout for how
+ */
+
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextStyle{
+    // pixels per em
+    pub size: f32,
+    pub color: Color,
+    // pixels from one baseline to the next,
+    // None uses the spacing the font asks for
+    pub line_height: Option<f32>,
+    pub align: TextAlign,
+    pub overflow: TextOverflow
+}
+
+// A glyph placed relative to the text's top left corner
+struct GlyphQuad{
+    corner: [f32; 2],
+    glyph: AtlasGlyph
+}
+
+pub struct TextComponent{
+    top_left_corner: [f32; 2],
+    text: String,
+    style: TextStyle,
+    // where every glyph went, and what part of the text it's from
+    layout: TextLayout,
+    // Glyphs are cut off outside of this, relative to the top left corner
+    clip: Option<[f32; 2]>,
+    font: Font,
+    // Each glyph and its pen position on the baseline,
+    // relative to the top left corner
+    placed: Vec<(GlyphKey, [f32; 2])>,
+    atlas: Rc<RefCell<GlyphAtlas>>,
+    // Where placed ended up in the atlas as of the last pre_render
+    quads: Vec<GlyphQuad>,
+    opacity: f32
+}
+
+impl TextComponent{
+    // max_size is the box the text wraps and overflows in,
+    // None means it can go on forever that way
+    // Nothing is rasterized until the first pre_render
+    pub fn new(
+        corner: [f32; 2],
+        text: &str,
+        font: &Font,
+        style: &TextStyle,
+        max_size: [Option<f32>; 2],
+        atlas: Rc<RefCell<GlyphAtlas>>
+    ) -> Self{
+        let mut text_component = Self{
+            top_left_corner: corner,
+            text: String::from(text),
+            style: *style,
+            layout: TextLayout::default(),
+            clip: None,
+            font: font.clone(),
+            placed: Vec::new(),
+            atlas,
+            quads: Vec::new(),
+            opacity: 1.0
+        };
+        text_component.lay_out(max_size);
+        text_component
+    }
+
+    fn lay_out(&mut self, max_size: [Option<f32>; 2]){
+        self.layout = layout(&self.font, &self.style, &self.text, max_size);
+        self.placed = self.layout.glyphs.iter()
+            .map(|glyph| (GlyphKey::new(&self.font, glyph.id, self.style.size), glyph.pen))
+            .collect();
+        self.clip = match self.style.overflow{
+            TextOverflow::Clip => Some(max_size.map(|max| max.unwrap_or(f32::INFINITY))),
+            TextOverflow::Visible | TextOverflow::Ellipsis => None,
+        };
+        // pre_render finds the new glyphs in the atlas
+        self.quads.clear();
+    }
+
+    // The string index a cursor clicked at a point goes at
+    // Nothing edits text yet, this and caret_position are for when something does
+    pub fn index_at(&self, point: [f32; 2]) -> usize{
+        let [x, y] = self.top_left_corner;
+        self.layout.index_at([point[0] - x, point[1] - y])
+    }
+
+    // Where to draw a cursor at a string index, its top and height
+    pub fn caret_position(&self, index: usize) -> Option<([f32; 2], f32)>{
+        let ([x, y], height) = self.layout.caret_position(index)?;
+        Some(([x + self.top_left_corner[0], y + self.top_left_corner[1]], height))
+    }
+}
+
+impl ComponentObject for TextComponent{
+    fn pre_render(&mut self) {
+        let mut atlas = self.atlas.borrow_mut();
+        self.quads = self.placed.iter()
+            .filter_map(|(key, pen)| {
+                // spaces and such have nothing to draw
+                let glyph = atlas.get_or_insert(&self.font, *key)?;
+                Some(GlyphQuad{
+                    corner: [pen[0] + glyph.offset[0], pen[1] + glyph.offset[1]],
+                    glyph
+                })
+            })
+            .collect();
+    }
+
+    fn get_instances(&self) -> Vec<QuadInstance> {
+        let atlas = self.atlas.borrow();
+        self.quads.iter().filter_map(|quad| {
+            // pages only get a texture once they're uploaded
+            let texture = atlas.page_texture(quad.glyph.page)?;
+            let [mut left, mut top] = quad.corner;
+            let [mut right, mut bottom] = [left + quad.glyph.size[0], top + quad.glyph.size[1]];
+            let [[mut u0, mut v0], [mut u1, mut v1]] = quad.glyph.texture_coords;
+            if let Some([clip_width, clip_height]) = self.clip{
+                // cut the quad down to the box, and the texture along with it
+                let (du, dv) = ((u1 - u0) / (right - left), (v1 - v0) / (bottom - top));
+                if right > clip_width{
+                    u1 -= (right - clip_width) * du;
+                    right = clip_width;
+                }
+                if bottom > clip_height{
+                    v1 -= (bottom - clip_height) * dv;
+                    bottom = clip_height;
+                }
+                if left < 0.0{
+                    u0 -= left * du;
+                    left = 0.0;
+                }
+                if top < 0.0{
+                    v0 -= top * dv;
+                    top = 0.0;
+                }
+                if left >= right || top >= bottom{
+                    return None;
+                }
+            }
+            let [x, y] = self.top_left_corner;
+            let (left, right, top, bottom) = (x + left, x + right, y + top, y + bottom);
+            Some(QuadInstance::textured([[left, top], [right, bottom]], texture, [[u0, v0], [u1, v1]])
+                // the glyphs in the atlas are white, this colors them in
+                .tinted(self.style.color)
+                .faded(self.opacity))
+        }).collect()
+    }
+
+    fn position(&self) -> [f32; 2] {
+        self.top_left_corner
+    }
+
+    fn set_position(&mut self, corner: [f32; 2]) {
+        self.top_left_corner = corner;
+    }
+
+    // Wraps again to fit the new box
+    fn set_size(&mut self, size: [f32; 2]) {
+        self.lay_out(size.map(Some));
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+}
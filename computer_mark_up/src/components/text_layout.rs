@@ -0,0 +1,738 @@
+use std::ops::Range;
+
+use ab_glyph::{Font as _, GlyphId, ScaleFont};
+use rustybuzz::{Direction, UnicodeBuffer};
+use unicode_bidi::{Level, ParagraphBidiInfo};
+use unicode_script::{Script, UnicodeScript};
+
+use crate::render::font::Font;
+
+use super::TextStyle;
+
+/*
+ * Turns a string into glyphs placed on lines
+ * Text is split into paragraphs on '\n', and each paragraph goes through
+ * the unicode bidi algorithm to find the parts that read right to left
+ * It's then cut into runs of one direction and script, and each run is
+ * shaped with rustybuzz, which takes care of kerning, ligatures,
+ * combining marks and letters that join up
+ *
+ * Lines are wrapped in logical order at the spaces between words,
+ * words too long for a line on their own are broken between clusters,
+ * and then each line is put in the order it's drawn, left to right
+ *
+ * Lines are lined up with the align mode, and cut short
+ * with an ellipsis if they don't fit the height and that's asked for
+ * Every glyph remembers which part of the text it came from,
+ * so points can be turned back into string indices for cursors
+ */
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign{
+    // The side the paragraph reads from,
+    // left for english and right for arabic or hebrew
+    #[default]
+    Start,
+    End,
+    Left,
+    Center,
+    Right,
+    // Spaces are stretched so lines fill the whole width,
+    // except for the last line of a paragraph
+    Justify,
+}
+
+// What happens to text that doesn't fit in its box
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextOverflow{
+    // Drawn anyway
+    #[default]
+    Visible,
+    // Cut off at the edges of the box
+    Clip,
+    // Lines that don't fit are dropped and the last
+    // one that does ends with '…'
+    Ellipsis,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositionedGlyph{
+    pub id: GlyphId,
+    // The pen position on the baseline, relative to the top left corner
+    pub pen: [f32; 2],
+    // Left edge and width of the room the glyph takes up on its line
+    pub left: f32,
+    pub advance: f32,
+    // Byte range of the text the glyph was made from,
+    // a ligature covers a few characters and a character can be a few glyphs
+    pub cluster: usize,
+    pub cluster_end: usize,
+    pub rtl: bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutLine{
+    // Into TextLayout.glyphs, in the order they're drawn
+    pub glyphs: Range<usize>,
+    // Bytes of the text on the line, including spaces dropped where it wrapped
+    pub text: Range<usize>,
+    pub top: f32,
+    pub bottom: f32,
+    // Where the line starts once it's aligned, for lines with nothing on them
+    pub left: f32
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextLayout{
+    pub glyphs: Vec<PositionedGlyph>,
+    pub lines: Vec<LayoutLine>,
+    // The widest line and the height of the lines that were kept
+    pub size: [f32; 2],
+    // Height of every line the text wrapped to,
+    // including any dropped for an ellipsis
+    pub content_height: f32
+}
+
+impl TextLayout{
+    // The string index a cursor at the point would go at,
+    // the point is relative to the top left corner
+    pub fn index_at(&self, point: [f32; 2]) -> usize{
+        let Some(line) = self.lines.iter().find(|line| point[1] < line.bottom).or(self.lines.last()) else {
+            return 0;
+        };
+        let glyphs = &self.glyphs[line.glyphs.clone()];
+        for glyph in glyphs{
+            if point[0] < glyph.left + glyph.advance{
+                // the left half of a right to left glyph is its end
+                let left_half = point[0] < glyph.left + glyph.advance / 2.0;
+                return if left_half != glyph.rtl { glyph.cluster } else { glyph.cluster_end };
+            }
+        }
+        match glyphs.last(){
+            Some(glyph) if glyph.rtl => glyph.cluster,
+            Some(glyph) => glyph.cluster_end,
+            None => line.text.start,
+        }
+    }
+
+    // The top of a cursor in front of the character at index, and how tall it is
+    // None if the index is past the end of what was laid out
+    pub fn caret_position(&self, index: usize) -> Option<([f32; 2], f32)>{
+        // where a line wrapped, the index goes at the start of the next one
+        let line = self.lines.iter().find(|line| line.text.contains(&index))
+            .or_else(|| self.lines.iter().find(|line| line.text.end == index))?;
+        let glyphs = &self.glyphs[line.glyphs.clone()];
+
+        // the front edge of whatever starts at index,
+        // or failing that the back edge of whatever ends there
+        let edge = |rtl: bool, front: bool, glyph: &PositionedGlyph| if rtl == front {
+            glyph.left + glyph.advance
+        } else {
+            glyph.left
+        };
+        let covering: Vec<&PositionedGlyph> = glyphs.iter()
+            .filter(|glyph| glyph.cluster <= index && index < glyph.cluster_end)
+            .collect();
+        let ending: Vec<&PositionedGlyph> = glyphs.iter()
+            .filter(|glyph| glyph.cluster_end == index)
+            .collect();
+        let x = match (covering.first(), ending.first()){
+            (Some(first), _) => {
+                let edges = covering.iter().map(|glyph| edge(first.rtl, true, glyph));
+                if first.rtl { edges.fold(f32::MIN, f32::max) } else { edges.fold(f32::MAX, f32::min) }
+            },
+            (None, Some(first)) => {
+                let edges = ending.iter().map(|glyph| edge(first.rtl, false, glyph));
+                if first.rtl { edges.fold(f32::MAX, f32::min) } else { edges.fold(f32::MIN, f32::max) }
+            },
+            (None, None) => line.left,
+        };
+        Some(([x, line.top], line.bottom - line.top))
+    }
+}
+
+// A glyph before it's been put on a line
+#[derive(Copy, Clone, Debug)]
+struct ShapedGlyph{
+    id: GlyphId,
+    advance: f32,
+    // from the pen to where the glyph is drawn, for marks and such
+    offset: [f32; 2],
+    cluster: usize,
+    cluster_end: usize,
+    whitespace: bool,
+    level: Level
+}
+
+struct Line{
+    // in logical order until they're reordered for drawing
+    glyphs: Vec<ShapedGlyph>,
+    // Last line of a paragraph, which justify leaves alone
+    ends_paragraph: bool,
+    text: Range<usize>,
+    // The paragraph reads right to left
+    rtl: bool
+}
+
+impl Line{
+    fn width(&self) -> f32{
+        advance(&self.glyphs)
+    }
+}
+
+fn advance(glyphs: &[ShapedGlyph]) -> f32{
+    glyphs.iter().map(|glyph| glyph.advance).sum()
+}
+
+// Splits a paragraph wherever the direction or script changes
+// Characters every script uses, like spaces and punctuation,
+// go along with whatever they're next to
+fn runs(paragraph: &str, levels: &[Level]) -> Vec<Range<usize>>{
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut script: Option<Script> = None;
+    for (i, c) in paragraph.char_indices(){
+        let char_script = match c.script(){
+            Script::Common | Script::Inherited | Script::Unknown => None,
+            char_script => Some(char_script),
+        };
+        let new_script = char_script.is_some_and(|char_script| script.is_some_and(|script| script != char_script));
+        if i > start && (levels[i] != levels[start] || new_script){
+            runs.push(start..i);
+            start = i;
+            script = None;
+        }
+        script = script.or(char_script);
+    }
+    if start < paragraph.len(){
+        runs.push(start..paragraph.len());
+    }
+    runs
+}
+
+// The glyphs for a paragraph in logical order, and which way it reads
+// start is where the paragraph is in the whole text
+fn shape(font: &Font, size: f32, paragraph: &str, start: usize) -> (Vec<ShapedGlyph>, Level){
+    let bidi = ParagraphBidiInfo::new(paragraph, None);
+    let face = font.face();
+    let scale = font.px_per_unit(size);
+
+    let mut glyphs = Vec::new();
+    for run in runs(paragraph, &bidi.levels){
+        let level = bidi.levels[run.start];
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(&paragraph[run.clone()]);
+        buffer.set_direction(if level.is_rtl() { Direction::RightToLeft } else { Direction::LeftToRight });
+        buffer.guess_segment_properties();
+
+        let shaped = rustybuzz::shape(face, &[], buffer);
+        let mut run_glyphs: Vec<ShapedGlyph> = shaped.glyph_infos().iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, position)| {
+                let cluster = run.start + info.cluster as usize;
+                ShapedGlyph{
+                    id: GlyphId(info.glyph_id as u16),
+                    advance: position.x_advance as f32 * scale,
+                    // rustybuzz goes up from the baseline, the screen goes down
+                    offset: [position.x_offset as f32 * scale, -position.y_offset as f32 * scale],
+                    cluster: start + cluster,
+                    cluster_end: 0,
+                    whitespace: paragraph[cluster..].starts_with(char::is_whitespace),
+                    level
+                }
+            })
+            .collect();
+        // right to left runs come out in the order they're drawn
+        if level.is_rtl(){
+            run_glyphs.reverse();
+        }
+        glyphs.extend(run_glyphs);
+    }
+
+    // each cluster ends where the next one starts
+    let mut end = start + paragraph.len();
+    let mut next_cluster = end;
+    for glyph in glyphs.iter_mut().rev(){
+        if glyph.cluster != next_cluster{
+            end = next_cluster;
+            next_cluster = glyph.cluster;
+        }
+        glyph.cluster_end = end;
+    }
+    (glyphs, bidi.paragraph_level)
+}
+
+// Greedily fills lines with as many words as fit in max_width
+// Spaces where a line wraps are dropped
+fn break_paragraph(glyphs: &[ShapedGlyph], max_width: Option<f32>, text: Range<usize>, rtl: bool, lines: &mut Vec<Line>){
+    let first_line = lines.len();
+    let mut finish = |glyphs: Vec<ShapedGlyph>, ends_paragraph: bool| {
+        let start = if lines.len() == first_line {
+            text.start
+        } else {
+            glyphs.first().map_or(text.end, |glyph| glyph.cluster)
+        };
+        // the end is filled in once the next line's start is known
+        lines.push(Line{ glyphs, ends_paragraph, text: start..start, rtl });
+    };
+
+    let mut line: Vec<ShapedGlyph> = Vec::new();
+    let mut width = 0.0;
+    // spaces waiting for the word after them
+    let mut pending: &[ShapedGlyph] = &[];
+
+    for run in glyphs.chunk_by(|a, b| a.whitespace == b.whitespace){
+        if run[0].whitespace{
+            pending = run;
+            continue;
+        }
+        let word_width = advance(run);
+        if let Some(max_width) = max_width{
+            if !line.is_empty() && width + advance(pending) + word_width > max_width{
+                finish(std::mem::take(&mut line), false);
+                width = 0.0;
+                pending = &[];
+            }
+        }
+        line.extend_from_slice(pending);
+        width += advance(pending);
+        pending = &[];
+
+        match max_width{
+            // too long for a line by itself
+            Some(max_width) if width + word_width > max_width => {
+                for glyph in run{
+                    // never split a cluster, its glyphs only make sense together
+                    let new_cluster = line.last().is_some_and(|last| last.cluster != glyph.cluster);
+                    if new_cluster && width + glyph.advance > max_width{
+                        finish(std::mem::take(&mut line), false);
+                        width = 0.0;
+                    }
+                    line.push(*glyph);
+                    width += glyph.advance;
+                }
+            },
+            _ => {
+                line.extend_from_slice(run);
+                width += word_width;
+            },
+        }
+    }
+    finish(line, true);
+
+    for i in first_line..lines.len(){
+        lines[i].text.end = lines.get(i + 1).map_or(text.end, |next| next.text.start);
+    }
+}
+
+// Ends the line with '…', taking glyphs off the end until it fits
+// cut is where the text that was left out starts
+fn ellipsize(font: &Font, size: f32, line: &mut Line, max_width: Option<f32>, cut: usize, text_end: usize){
+    let scaled = font.inner().as_scaled(font.px_scale(size));
+    let id = scaled.glyph_id('…');
+    let mut ellipsis = ShapedGlyph{
+        id,
+        advance: scaled.h_advance(id),
+        offset: [0.0, 0.0],
+        cluster: cut,
+        cluster_end: text_end,
+        whitespace: false,
+        level: if line.rtl { Level::rtl() } else { Level::ltr() }
+    };
+    if let Some(max_width) = max_width{
+        while !line.glyphs.is_empty() && line.width() + ellipsis.advance > max_width{
+            ellipsis.cluster = line.glyphs.pop().map_or(cut, |glyph| glyph.cluster);
+        }
+    }
+    while line.glyphs.last().is_some_and(|glyph| glyph.whitespace){
+        line.glyphs.pop();
+    }
+    line.glyphs.push(ellipsis);
+    line.ends_paragraph = true;
+}
+
+// Puts a line's glyphs in the order they're drawn, left to right
+// From the highest level down to the lowest odd one,
+// every stretch of glyphs at that level or above is reversed
+fn reorder(glyphs: &mut [ShapedGlyph]){
+    let levels = || glyphs.iter().map(|glyph| glyph.level.number());
+    let (Some(highest), Some(lowest)) = (levels().max(), levels().min()) else {
+        return;
+    };
+    let lowest_odd = lowest | 1;
+    for level in (lowest_odd..=highest).rev(){
+        let mut i = 0;
+        while i < glyphs.len(){
+            if glyphs[i].level.number() < level{
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < glyphs.len() && glyphs[i].level.number() >= level{
+                i += 1;
+            }
+            glyphs[start..i].reverse();
+        }
+    }
+}
+
+// max_size is the box the text goes in,
+// None means there's no limit that way
+pub fn layout(font: &Font, style: &TextStyle, text: &str, max_size: [Option<f32>; 2]) -> TextLayout{
+    let scaled = font.inner().as_scaled(font.px_scale(style.size));
+    let line_height = style.line_height.unwrap_or(scaled.height() + scaled.line_gap());
+    let lines_height = |count: usize| if count == 0 {
+        0.0
+    } else {
+        (count - 1) as f32 * line_height + scaled.height()
+    };
+    let [max_width, max_height] = max_size;
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for paragraph in text.split('\n'){
+        let (glyphs, level) = shape(font, style.size, paragraph, start);
+        let range = start..start + paragraph.len();
+        break_paragraph(&glyphs, max_width, range, level.is_rtl(), &mut lines);
+        start += paragraph.len() + 1;
+    }
+    if text.is_empty(){
+        lines.clear();
+    }
+    let content_height = lines_height(lines.len());
+
+    if let (TextOverflow::Ellipsis, Some(max_height)) = (style.overflow, max_height){
+        // always keep one line so there's somewhere for the '…' to go
+        let fits = if max_height < scaled.height() {
+            1
+        } else {
+            ((max_height - scaled.height()) / line_height).floor() as usize + 1
+        };
+        if fits < lines.len(){
+            let cut = lines[fits].text.start;
+            lines.truncate(fits);
+            if let Some(last) = lines.last_mut(){
+                ellipsize(font, style.size, last, max_width, cut, text.len());
+                last.text.end = text.len();
+            }
+        }
+    }
+
+    let widest = lines.iter().map(Line::width).fold(0.0f32, f32::max);
+    let box_width = max_width.unwrap_or(widest);
+    let mut glyphs = Vec::new();
+    let mut laid_out = Vec::new();
+    for (line_index, line) in lines.iter_mut().enumerate(){
+        reorder(&mut line.glyphs);
+        // pens sit on whole pixels so glyphs line up with the atlas texels
+        let top = line_index as f32 * line_height;
+        let baseline = (scaled.ascent() + top).round();
+        let spare = (box_width - line.width()).max(0.0);
+        // spaces at the start of a line aren't a gap between words
+        let first_word = line.glyphs.iter().position(|glyph| !glyph.whitespace).unwrap_or(0);
+        let is_gap = |i: usize| i > first_word && line.glyphs[i - 1].whitespace && !line.glyphs[i].whitespace;
+        let gaps = (0..line.glyphs.len()).filter(|&i| is_gap(i)).count();
+
+        let justify = style.align == TextAlign::Justify && !line.ends_paragraph && gaps > 0;
+        let stretch = if justify { spare / gaps as f32 } else { 0.0 };
+        let mut caret = match style.align{
+            _ if justify => 0.0,
+            TextAlign::Left => 0.0,
+            TextAlign::Center => spare / 2.0,
+            TextAlign::Right => spare,
+            // lines justify leaves alone go at the start
+            TextAlign::Start | TextAlign::Justify => if line.rtl { spare } else { 0.0 },
+            TextAlign::End => if line.rtl { 0.0 } else { spare },
+        };
+
+        let line_start = glyphs.len();
+        let left = caret;
+        for (i, glyph) in line.glyphs.iter().enumerate(){
+            if is_gap(i){
+                caret += stretch;
+            }
+            glyphs.push(PositionedGlyph{
+                id: glyph.id,
+                pen: [(caret + glyph.offset[0]).round(), (baseline + glyph.offset[1]).round()],
+                left: caret,
+                advance: glyph.advance,
+                cluster: glyph.cluster,
+                cluster_end: glyph.cluster_end,
+                rtl: glyph.level.is_rtl()
+            });
+            caret += glyph.advance;
+        }
+        laid_out.push(LayoutLine{
+            glyphs: line_start..glyphs.len(),
+            text: line.text.clone(),
+            top,
+            bottom: top + scaled.height(),
+            left
+        });
+    }
+
+    TextLayout{
+        glyphs,
+        size: [widest.ceil(), lines_height(laid_out.len()).ceil()],
+        lines: laid_out,
+        content_height: content_height.ceil()
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::render::color::Color;
+
+    fn lay_out(text: &str) -> TextLayout{
+        let style = TextStyle{
+            size: 20.0,
+            color: Color::WHITE,
+            line_height: None,
+            align: TextAlign::Start,
+            overflow: TextOverflow::Visible
+        };
+        layout(&Font::default_font(), &style, text, [None, None])
+    }
+
+    const LINE_HEIGHT: f32 = 24.0;
+
+    fn lay_out_in(text: &str, align: TextAlign, overflow: TextOverflow, max_size: [Option<f32>; 2]) -> TextLayout{
+        let style = TextStyle{
+            size: 20.0,
+            color: Color::WHITE,
+            line_height: Some(LINE_HEIGHT),
+            align,
+            overflow
+        };
+        layout(&Font::default_font(), &style, text, max_size)
+    }
+
+    fn wrapped(text: &str, max_width: f32) -> TextLayout{
+        lay_out_in(text, TextAlign::Start, TextOverflow::Visible, [Some(max_width), None])
+    }
+
+    // How wide the first count glyphs of the text are on one line
+    fn width_of(text: &str, count: usize) -> f32{
+        lay_out(text).glyphs[..count].iter().map(|glyph| glyph.advance).sum()
+    }
+
+    fn line_texts<'a>(layout: &TextLayout, text: &'a str) -> Vec<&'a str>{
+        layout.lines.iter().map(|line| &text[line.text.clone()]).collect()
+    }
+
+    // Where the line's last glyph ends
+    fn right_edge(layout: &TextLayout, line: usize) -> f32{
+        let last = layout.glyphs[layout.lines[line].glyphs.clone()].last().unwrap();
+        last.left + last.advance
+    }
+
+    // Height of the font's tallest glyphs at size 20, what one line takes up
+    fn glyph_height() -> f32{
+        let font = Font::default_font();
+        font.inner().as_scaled(font.px_scale(20.0)).height()
+    }
+
+    #[test]
+    fn wraps_between_words(){
+        let text = "one two three";
+        let max_width = width_of(text, 7) + 1.0;
+        let layout = wrapped(text, max_width);
+        // the space it wrapped at belongs to the line before
+        assert_eq!(line_texts(&layout, text), ["one two ", "three"]);
+        assert!(right_edge(&layout, 0) <= max_width);
+        assert_eq!(layout.glyphs[layout.lines[1].glyphs.start].left, 0.0);
+        assert_eq!(layout.lines[1].top, LINE_HEIGHT);
+    }
+
+    #[test]
+    fn long_words_break_between_characters(){
+        let text = "abcdefghij";
+        let max_width = width_of(text, 4) + 0.5;
+        let layout = wrapped(text, max_width);
+        assert_eq!(line_texts(&layout, text), ["abcd", "efgh", "ij"]);
+        for line in 0..3{
+            assert!(right_edge(&layout, line) <= max_width);
+        }
+        // and a word after it still goes on the line if it fits
+        let layout = wrapped("abcdefghij k", max_width);
+        assert_eq!(line_texts(&layout, "abcdefghij k"), ["abcd", "efgh", "ij k"]);
+    }
+
+    #[test]
+    fn newlines_start_new_lines(){
+        let text = "ab\ncd\n\nef";
+        let layout = lay_out_in(text, TextAlign::Start, TextOverflow::Visible, [None, None]);
+        assert_eq!(line_texts(&layout, text), ["ab", "cd", "", "ef"]);
+        let tops: Vec<f32> = layout.lines.iter().map(|line| line.top).collect();
+        assert_eq!(tops, [0.0, 24.0, 48.0, 72.0]);
+        // the empty line is still there, it just has nothing on it
+        assert!(layout.lines[2].glyphs.is_empty());
+        assert_eq!(layout.content_height, (3.0 * LINE_HEIGHT + glyph_height()).ceil());
+        assert_eq!(layout.size[1], layout.content_height);
+    }
+
+    #[test]
+    fn empty_text_takes_no_room(){
+        let layout = lay_out("");
+        assert!(layout.lines.is_empty());
+        assert_eq!(layout.size, [0.0, 0.0]);
+        assert_eq!(layout.content_height, 0.0);
+    }
+
+    #[test]
+    fn align_offsets(){
+        let width = width_of("ab", 2);
+        let left = |align: TextAlign| {
+            let layout = lay_out_in("ab", align, TextOverflow::Visible, [Some(100.0), None]);
+            layout.glyphs[0].left
+        };
+        assert_eq!(left(TextAlign::Left), 0.0);
+        assert_eq!(left(TextAlign::Start), 0.0);
+        assert_eq!(left(TextAlign::Center), (100.0 - width) / 2.0);
+        assert_eq!(left(TextAlign::Right), 100.0 - width);
+        assert_eq!(left(TextAlign::End), 100.0 - width);
+
+        // right to left text starts on the right
+        let rtl = |align: TextAlign| {
+            let layout = lay_out_in("\u{5d0}\u{5d1}", align, TextOverflow::Visible, [Some(100.0), None]);
+            right_edge(&layout, 0)
+        };
+        assert_eq!(rtl(TextAlign::Start), 100.0);
+        assert!(rtl(TextAlign::End) < 100.0);
+    }
+
+    #[test]
+    fn justify_fills_every_line_but_the_last(){
+        let text = "aa bb cc dd";
+        let max_width = width_of(text, 8) + 5.0;
+        let layout = lay_out_in(text, TextAlign::Justify, TextOverflow::Visible, [Some(max_width), None]);
+        assert_eq!(line_texts(&layout, text), ["aa bb cc ", "dd"]);
+        assert!((right_edge(&layout, 0) - max_width).abs() < 0.001);
+        // the space it wrapped at was dropped, so it isn't stretched into the edge
+        assert_eq!(layout.lines[0].glyphs.len(), 8);
+        // the last line of a paragraph is left alone
+        assert_eq!(layout.glyphs[layout.lines[1].glyphs.start].left, 0.0);
+        assert_eq!(right_edge(&layout, 1), width_of("dd", 2));
+    }
+
+    #[test]
+    fn clip_keeps_every_line_and_ellipsis_cuts_them(){
+        let text = "one two three four";
+        let max_size = [Some(width_of(text, 7) + 1.0), Some(30.0)];
+        let clipped = lay_out_in(text, TextAlign::Start, TextOverflow::Clip, max_size);
+        let ellipsized = lay_out_in(text, TextAlign::Start, TextOverflow::Ellipsis, max_size);
+
+        // clipping is done when it's drawn, every line is still laid out
+        assert_eq!(line_texts(&clipped, text), ["one two ", "three ", "four"]);
+        assert_eq!(clipped.size[1], (2.0 * LINE_HEIGHT + glyph_height()).ceil());
+
+        // only one line fits in 30 pixels, and it ends with '…'
+        assert_eq!(ellipsized.lines.len(), 1);
+        assert_eq!(ellipsized.size[1], glyph_height().ceil());
+        let font = Font::default_font();
+        let ellipsis = ellipsized.glyphs.last().unwrap();
+        assert_eq!(ellipsis.id, font.inner().glyph_id('…'));
+        // it stands for everything that was left out
+        assert_eq!(ellipsis.cluster_end, text.len());
+        assert_eq!(ellipsized.lines[0].text, 0..text.len());
+        assert!(right_edge(&ellipsized, 0) <= max_size[0].unwrap());
+
+        // both measure all of the text
+        assert_eq!(clipped.content_height, ellipsized.content_height);
+    }
+
+    fn caret_x(layout: &TextLayout, index: usize) -> f32{
+        layout.caret_position(index).unwrap().0[0]
+    }
+
+    // The edge a cursor in front of the glyph's text goes at
+    fn front(glyph: &PositionedGlyph) -> f32{
+        if glyph.rtl { glyph.left + glyph.advance } else { glyph.left }
+    }
+
+    // Every glyph's text starts at its front edge, both ways round
+    fn assert_round_trips(layout: &TextLayout){
+        for glyph in &layout.glyphs{
+            assert_eq!(caret_x(layout, glyph.cluster), front(glyph), "{:?}", glyph);
+            let just_inside = if glyph.rtl { front(glyph) - 0.5 } else { front(glyph) + 0.5 };
+            assert_eq!(layout.index_at([just_inside, 5.0]), glyph.cluster, "{:?}", glyph);
+        }
+    }
+
+    #[test]
+    fn left_to_right(){
+        let layout = lay_out("abc");
+        let [a, b, c] = [0, 1, 2].map(|i| layout.glyphs[i]);
+        assert!(!a.rtl);
+        assert_eq!(caret_x(&layout, 0), 0.0);
+        assert_eq!(caret_x(&layout, 1), b.left);
+        assert_eq!(caret_x(&layout, 3), c.left + c.advance);
+        assert_eq!(layout.caret_position(4), None);
+
+        assert_eq!(layout.index_at([-5.0, 5.0]), 0);
+        // the left half of a glyph is in front of it, the right half after it
+        assert_eq!(layout.index_at([b.left + 1.0, 5.0]), 1);
+        assert_eq!(layout.index_at([b.left + b.advance - 1.0, 5.0]), 2);
+        assert_eq!(layout.index_at([500.0, 5.0]), 3);
+        assert_round_trips(&layout);
+    }
+
+    #[test]
+    fn right_to_left(){
+        // alef bet gimel, two bytes each
+        let layout = lay_out("\u{5d0}\u{5d1}\u{5d2}");
+        assert!(layout.glyphs.iter().all(|glyph| glyph.rtl));
+        // drawn the other way round, so the first letter is on the right
+        assert_eq!(layout.glyphs.last().unwrap().cluster, 0);
+        let width = layout.glyphs.iter().map(|glyph| glyph.advance).sum::<f32>();
+        assert_eq!(caret_x(&layout, 0), width);
+        assert_eq!(caret_x(&layout, 6), 0.0);
+        assert!(caret_x(&layout, 2) < caret_x(&layout, 0));
+        assert!(caret_x(&layout, 4) < caret_x(&layout, 2));
+
+        assert_eq!(layout.index_at([width - 1.0, 5.0]), 0);
+        assert_eq!(layout.index_at([1.0, 5.0]), 6);
+        // past the left end is the end of the text
+        assert_eq!(layout.index_at([-5.0, 5.0]), 6);
+        assert_round_trips(&layout);
+    }
+
+    #[test]
+    fn mixed_directions(){
+        // english with a hebrew word in the middle
+        let layout = lay_out("ab \u{5d0}\u{5d1} cd");
+        let rtl: Vec<bool> = layout.glyphs.iter().map(|glyph| glyph.rtl).collect();
+        assert_eq!(rtl, [false, false, false, true, true, false, false, false]);
+        // the hebrew reads right to left inside the left to right line
+        assert!(caret_x(&layout, 3) > caret_x(&layout, 5));
+        assert!(caret_x(&layout, 2) < caret_x(&layout, 5));
+        assert!(caret_x(&layout, 8) > caret_x(&layout, 3));
+        assert_round_trips(&layout);
+    }
+
+    // Every index points must turn into, from one end of the line to the other
+    fn indices_across(layout: &TextLayout) -> Vec<usize>{
+        let mut indices: Vec<usize> = (-10..200).map(|x| layout.index_at([x as f32 / 2.0, 5.0])).collect();
+        indices.sort();
+        indices.dedup();
+        indices
+    }
+
+    #[test]
+    fn indices_inside_a_cluster_snap_to_its_edge(){
+        // e and a combining acute accent are one cluster, bytes 0 to 3
+        let layout = lay_out("e\u{301}x");
+        assert_eq!(layout.glyphs[0].cluster..layout.glyphs[0].cluster_end, 0..3);
+        assert_eq!(caret_x(&layout, 1), caret_x(&layout, 0));
+        assert_eq!(caret_x(&layout, 2), caret_x(&layout, 0));
+        assert_eq!(indices_across(&layout), [0, 3, 4]);
+
+        // lam alef is a single ligature glyph in arabic
+        let layout = lay_out("\u{644}\u{627}");
+        assert_eq!(layout.glyphs.len(), 1);
+        assert_eq!(caret_x(&layout, 2), caret_x(&layout, 0));
+        assert_eq!(indices_across(&layout), [0, 4]);
+    }
+}
@@ -0,0 +1,478 @@
+/*
+ * Parser for the definition attribute
+ * ie definition="x:100; y:200; width:400; height:600; c:(0,0,255);"
+ *
+ * A definition is a list of key:value pairs seperated by ';'
+ * Values are either a number, a word or a tuple of numbers
+ * Whitespace is allowed anywhere between the pieces
+ * and the last ';' is optional
+ */
+
+use super::error::{ReadError, ReadErrorKind};
+use super::tokenizer::Cursor;
+
+// Every property a definition can set
+// Anything left as None wasn't in the definition
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Definition{
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub color: Option<Rgba>,
+    // The color a hover tag changes to when the mouse is over it
+    pub hover_color: Option<Rgba>,
+    // Text size in pixels
+    pub font_size: Option<f32>,
+    // Pixels from one line of text's baseline to the next
+    pub line_height: Option<f32>,
+    // How lines of text line up in their box, start, end, left, center, right or justify
+    pub align: Option<Align>,
+    // What text that doesn't fit its box does, visible, clip or ellipsis
+    pub overflow: Option<Overflow>,
+    // How an image is scaled into its box, stretch, contain, cover or none
+    pub fit: Option<Fit>,
+    // How round the corners of a square, hover or image are in pixels
+    pub corner_radius: Option<f32>,
+    // From 0.0 for invisible to 1.0 for solid, everything inside fades along with it
+    // Each tag is faded on its own, so where a faded tag's children overlap
+    // each other or the tag, the parts underneath show through
+    pub opacity: Option<f32>,
+    // Which layer a tag and everything inside it is drawn on, higher is on top
+    // Tags without one are on their parent's layer, and the top level is on 0
+    pub z_index: Option<i32>,
+    // Keys that were parsed but mean nothing,
+    // kept around so they can be warned about
+    pub unknown_keys: Vec<UnknownKey>,
+}
+
+impl Definition{
+    pub fn position(&self) -> [f32; 2]{
+        [self.x.unwrap_or(0.0), self.y.unwrap_or(0.0)]
+    }
+
+    pub fn size(&self) -> [f32; 2]{
+        [self.width.unwrap_or(0.0), self.height.unwrap_or(0.0)]
+    }
+}
+
+/*
+ * Values are kept as plain data here, scene_state
+ * turns them into whatever the components use
+ */
+
+// Red, green, blue and alpha, each from 0.0 to 1.0
+pub type Rgba = [f32; 4];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Align{
+    Start,
+    End,
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl Align{
+    pub fn from_name(name: &str) -> Option<Self>{
+        match name{
+            "start" => Some(Self::Start),
+            "end" => Some(Self::End),
+            "left" => Some(Self::Left),
+            "center" => Some(Self::Center),
+            "right" => Some(Self::Right),
+            "justify" => Some(Self::Justify),
+            _ => None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overflow{
+    Visible,
+    Clip,
+    Ellipsis,
+}
+
+impl Overflow{
+    pub fn from_name(name: &str) -> Option<Self>{
+        match name{
+            "visible" => Some(Self::Visible),
+            "clip" => Some(Self::Clip),
+            "ellipsis" => Some(Self::Ellipsis),
+            _ => None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fit{
+    Stretch,
+    Contain,
+    Cover,
+    None,
+}
+
+impl Fit{
+    pub fn from_name(name: &str) -> Option<Self>{
+        match name{
+            "stretch" => Some(Self::Stretch),
+            "contain" => Some(Self::Contain),
+            "cover" => Some(Self::Cover),
+            "none" => Some(Self::None),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownKey{
+    pub key: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Number{
+    value: f32,
+    // 1.0 and 1 mean different things for colors
+    is_float: bool,
+    // Read straight from the text when there's no '.', an f32
+    // can't hold every whole number past 2^24
+    whole: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value{
+    Number(Number),
+    Tuple(Vec<Number>),
+    Word(String),
+}
+
+fn bad_definition(message: String, (line, column): (usize, usize)) -> ReadError{
+    ReadError::at(ReadErrorKind::BadDefinition(message), line, column)
+}
+
+fn is_key_char(c: char) -> bool{
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+fn is_number_char(c: char) -> bool{
+    c.is_ascii_digit() || c == '.' || c == '-' || c == '+'
+}
+
+// line and column are where the definition text starts in the file
+pub(super) fn parse_definition(source: &str, line: usize, column: usize) -> Result<Definition, ReadError>{
+    let mut cursor = Cursor::starting_at(source, line, column);
+    let mut definition = Definition::default();
+
+    loop{
+        cursor.skip_whitespace();
+        if cursor.peek().is_none(){
+            break;
+        }
+
+        let key_position = cursor.position();
+        let key = cursor.take_while(is_key_char);
+        if key.is_empty(){
+            return Err(bad_definition(String::from("expected a property name"), key_position));
+        }
+
+        cursor.skip_whitespace();
+        if cursor.peek() != Some(':'){
+            return Err(bad_definition(format!("expected ':' after '{}'", key), cursor.position()));
+        }
+        cursor.bump();
+
+        cursor.skip_whitespace();
+        let value_position = cursor.position();
+        let value = parse_value(&mut cursor)?;
+
+        cursor.skip_whitespace();
+        match cursor.peek(){
+            Some(';') => { cursor.bump(); },
+            None => {},
+            Some(_) => return Err(bad_definition(
+                format!("expected ';' after the value of '{}'", key), cursor.position()
+            )),
+        }
+
+        apply_property(&mut definition, key, value, key_position, value_position)?;
+    }
+
+    Ok(definition)
+}
+
+// Parses a color on its own, outside of any definition
+// The brackets are optional, so 0.1,0.2,0.3 works as well as (25,50,75)
+pub(crate) fn parse_color(source: &str) -> Result<Rgba, ReadError>{
+    let trimmed = source.trim();
+    let bracketed = if trimmed.starts_with('('){
+        String::from(trimmed)
+    } else {
+        format!("({})", trimmed)
+    };
+    let mut cursor = Cursor::new(&bracketed);
+    let value = parse_value(&mut cursor)?;
+    if cursor.peek().is_some(){
+        return Err(bad_definition(format!("'{}' isn't a color", source), cursor.position()));
+    }
+    expect_color("color", value, (1, 1))
+}
+
+fn parse_value(cursor: &mut Cursor) -> Result<Value, ReadError>{
+    let position = cursor.position();
+    match cursor.peek(){
+        Some('(') => {
+            cursor.bump();
+            let mut numbers = Vec::new();
+            loop{
+                cursor.skip_whitespace();
+                numbers.push(parse_number(cursor)?);
+                cursor.skip_whitespace();
+                match cursor.bump(){
+                    Some(',') => {},
+                    Some(')') => break,
+                    _ => return Err(bad_definition(String::from("unclosed '(' in value"), position)),
+                }
+            }
+            Ok(Value::Tuple(numbers))
+        },
+        Some(c) if is_number_char(c) => Ok(Value::Number(parse_number(cursor)?)),
+        Some(c) if is_key_char(c) => Ok(Value::Word(cursor.take_while(is_key_char))),
+        _ => Err(bad_definition(String::from("expected a value"), position)),
+    }
+}
+
+fn parse_number(cursor: &mut Cursor) -> Result<Number, ReadError>{
+    let position = cursor.position();
+    let text = cursor.take_while(is_number_char);
+    match text.parse::<f32>(){
+        Ok(value) => {
+            let is_float = text.contains('.');
+            let whole = if is_float { None } else { text.parse::<i64>().ok() };
+            Ok(Number{ value, is_float, whole })
+        },
+        Err(_) => Err(bad_definition(format!("'{}' is not a number", text), position)),
+    }
+}
+
+fn apply_property(
+    definition: &mut Definition,
+    key: String,
+    value: Value,
+    key_position: (usize, usize),
+    value_position: (usize, usize)
+) -> Result<(), ReadError>{
+    match key.as_str(){
+        "x" => definition.x = Some(expect_number(&key, value, value_position)?),
+        "y" => definition.y = Some(expect_number(&key, value, value_position)?),
+        "width" => definition.width = Some(expect_size(&key, value, value_position)?),
+        "height" => definition.height = Some(expect_size(&key, value, value_position)?),
+        "c" | "color" => definition.color = Some(expect_color(&key, value, value_position)?),
+        "hover-c" | "hover-color" => definition.hover_color = Some(expect_color(&key, value, value_position)?),
+        "size" | "font-size" => definition.font_size = Some(expect_size(&key, value, value_position)?),
+        "line-height" => definition.line_height = Some(expect_size(&key, value, value_position)?),
+        "align" | "text-align" => definition.align = Some(
+            expect_word(&key, value, value_position, &["start", "end", "left", "center", "right", "justify"], Align::from_name)?
+        ),
+        "overflow" => definition.overflow = Some(
+            expect_word(&key, value, value_position, &["visible", "clip", "ellipsis"], Overflow::from_name)?
+        ),
+        "fit" => definition.fit = Some(
+            expect_word(&key, value, value_position, &["stretch", "contain", "cover", "none"], Fit::from_name)?
+        ),
+        "radius" | "corner-radius" => definition.corner_radius = Some(expect_size(&key, value, value_position)?),
+        "opacity" => definition.opacity = Some(expect_fraction(&key, value, value_position)?),
+        "z" | "z-index" => definition.z_index = Some(expect_integer(&key, value, value_position)?),
+        _ => definition.unknown_keys.push(UnknownKey{
+            key,
+            line: key_position.0,
+            column: key_position.1
+        }),
+    }
+    Ok(())
+}
+
+fn expect_number(key: &str, value: Value, position: (usize, usize)) -> Result<f32, ReadError>{
+    match value{
+        Value::Number(number) => Ok(number.value),
+        _ => Err(bad_definition(format!("'{}' needs to be a number", key), position)),
+    }
+}
+
+fn expect_size(key: &str, value: Value, position: (usize, usize)) -> Result<f32, ReadError>{
+    let size = expect_number(key, value, position)?;
+    if size < 0.0{
+        return Err(bad_definition(format!("'{}' can't be negative", key), position));
+    }
+    Ok(size)
+}
+
+fn expect_integer(key: &str, value: Value, position: (usize, usize)) -> Result<i32, ReadError>{
+    match value{
+        Value::Number(Number{ whole: Some(whole), .. }) => i32::try_from(whole).map_err(|_| bad_definition(
+            format!("'{}' needs to be between {} and {}", key, i32::MIN, i32::MAX),
+            position
+        )),
+        _ => Err(bad_definition(format!("'{}' needs to be a whole number", key), position)),
+    }
+}
+
+// A number from 0 to 1
+fn expect_fraction(key: &str, value: Value, position: (usize, usize)) -> Result<f32, ReadError>{
+    let fraction = expect_number(key, value, position)?;
+    if !(0.0..=1.0).contains(&fraction){
+        return Err(bad_definition(format!("'{}' needs to be between 0.0 and 1.0", key), position));
+    }
+    Ok(fraction)
+}
+
+// For properties that are one of a few words, options are all of them
+fn expect_word<T>(
+    key: &str,
+    value: Value,
+    position: (usize, usize),
+    options: &[&str],
+    from_name: fn(&str) -> Option<T>
+) -> Result<T, ReadError>{
+    match value{
+        Value::Word(word) => from_name(&word).ok_or_else(|| bad_definition(
+            format!("'{}' isn't a {}, it can be {}", word, key, options.join(", ")), position
+        )),
+        _ => Err(bad_definition(format!("'{}' needs to be one of {}", key, options.join(", ")), position)),
+    }
+}
+
+// Colors are (r,g,b) or (r,g,b,a)
+// Each channel is either an integer from 0 to 255
+// or a float from 0.0 to 1.0
+fn expect_color(key: &str, value: Value, position: (usize, usize)) -> Result<Rgba, ReadError>{
+    let channels = match value{
+        Value::Tuple(channels) if channels.len() == 3 || channels.len() == 4 => channels,
+        _ => return Err(bad_definition(
+            format!("'{}' needs to be a color like (r,g,b) or (r,g,b,a)", key), position
+        )),
+    };
+
+    let mut rgba = [1.0f32; 4];
+    for (i, channel) in channels.iter().enumerate(){
+        rgba[i] = if channel.is_float{
+            if !(0.0..=1.0).contains(&channel.value){
+                return Err(bad_definition(
+                    format!("color channel {} needs to be between 0.0 and 1.0", channel.value), position
+                ));
+            }
+            channel.value
+        } else {
+            if !(0.0..=255.0).contains(&channel.value){
+                return Err(bad_definition(
+                    format!("color channel {} needs to be between 0 and 255", channel.value), position
+                ));
+            }
+            channel.value / 255.0
+        };
+    }
+    Ok(rgba)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn parse(source: &str) -> Result<Definition, ReadError>{
+        parse_definition(source, 1, 1)
+    }
+
+    fn error_message(source: &str) -> String{
+        match parse(source).expect_err("the definition is broken").kind{
+            ReadErrorKind::BadDefinition(message) => message,
+            other => panic!("expected a bad definition, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn integer_channels_are_0_to_255(){
+        let definition = parse("c:(255,0,51);").unwrap();
+        assert_eq!(definition.color, Some([1.0, 0.0, 0.2, 1.0]));
+        let definition = parse("c:(255,0,51,0);").unwrap();
+        assert_eq!(definition.color, Some([1.0, 0.0, 0.2, 0.0]));
+    }
+
+    #[test]
+    fn float_channels_are_0_to_1(){
+        let definition = parse("c:(1.0,0.0,0.5,0.25);").unwrap();
+        assert_eq!(definition.color, Some([1.0, 0.0, 0.5, 0.25]));
+        // 1 and 1.0 aren't the same thing
+        let definition = parse("c:(1,1.0,0);").unwrap();
+        assert_eq!(definition.color, Some([1.0 / 255.0, 1.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn out_of_range_channels(){
+        assert!(error_message("c:(256,0,0);").contains("between 0 and 255"));
+        assert!(error_message("c:(0,-1,0);").contains("between 0 and 255"));
+        assert!(error_message("c:(0.0,1.5,0.0);").contains("between 0.0 and 1.0"));
+        assert!(error_message("c:(0,0);").contains("(r,g,b)"));
+        assert!(error_message("opacity:1.5;").contains("between 0.0 and 1.0"));
+    }
+
+    #[test]
+    fn trailing_semicolon_is_optional(){
+        let with = parse("x:10; y:20;").unwrap();
+        let without = parse("x:10; y:20").unwrap();
+        assert_eq!(with, without);
+        assert_eq!(with.position(), [10.0, 20.0]);
+    }
+
+    #[test]
+    fn whitespace_anywhere_between_pieces(){
+        let spaced = parse("  width :  400 ;\n\theight:\t600 ; c : ( 0 , 0 , 255 ) ;  ").unwrap();
+        let tight = parse("width:400;height:600;c:(0,0,255);").unwrap();
+        assert_eq!(spaced, tight);
+        assert_eq!(tight.size(), [400.0, 600.0]);
+    }
+
+    #[test]
+    fn negative_sizes(){
+        assert!(error_message("width:-10;").contains("can't be negative"));
+        assert!(error_message("font-size:-1;").contains("can't be negative"));
+        assert!(error_message("radius:-4;").contains("can't be negative"));
+        assert_eq!(parse("corner-radius:12;").unwrap().corner_radius, Some(12.0));
+        // positions can go off the top and left
+        assert_eq!(parse("x:-10;").unwrap().x, Some(-10.0));
+    }
+
+    #[test]
+    fn z_is_read_as_a_whole_number(){
+        assert_eq!(parse("z:-3;").unwrap().z_index, Some(-3));
+        assert_eq!(parse("z-index:+2;").unwrap().z_index, Some(2));
+        // too big for an f32 to hold exactly
+        assert_eq!(parse("z:16777217;").unwrap().z_index, Some(16777217));
+        assert_eq!(parse("z:2147483647;").unwrap().z_index, Some(i32::MAX));
+        assert!(error_message("z:2147483648;").contains("between"));
+        assert!(error_message("z:1.0;").contains("whole number"));
+        assert!(error_message("z:top;").contains("whole number"));
+    }
+
+    #[test]
+    fn unknown_keys_are_kept(){
+        let definition = parse("x:1;\nwobble:3; y:2;").unwrap();
+        assert_eq!(definition.y, Some(2.0));
+        assert_eq!(definition.unknown_keys, vec![UnknownKey{
+            key: String::from("wobble"),
+            line: 2,
+            column: 1
+        }]);
+    }
+
+    #[test]
+    fn errors_point_into_the_file(){
+        // as if the definition started at line 3, column 20
+        let error = parse_definition("x:1; y:oops;", 3, 20).unwrap_err();
+        assert_eq!((error.line, error.column), (3, 27));
+        let error = parse_definition("x:1 y:2", 3, 20).unwrap_err();
+        assert_eq!((error.line, error.column), (3, 24));
+    }
+}
@@ -0,0 +1,155 @@
+/*
+ * The typed tree that a .cmu file is parsed into.
+ * Everything after parsing (scene building, rendering)
+ * should only ever look at these types and never at raw text
+ */
+
+use std::path::{Path, PathBuf};
+
+use super::definition::Definition;
+
+// Every tag the markup language understands
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tag{
+    Main,
+    Square,
+    Hover,
+    Text,
+    Image,
+    Comment,
+}
+
+impl Tag{
+    pub fn from_name(name: &str) -> Option<Self>{
+        match name{
+            "main" => Some(Self::Main),
+            "square" => Some(Self::Square),
+            "hover" => Some(Self::Hover),
+            "text" => Some(Self::Text),
+            "image" => Some(Self::Image),
+            "comment" => Some(Self::Comment),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Self::Main => "main",
+            Self::Square => "square",
+            Self::Hover => "hover",
+            Self::Text => "text",
+            Self::Image => "image",
+            Self::Comment => "comment",
+        }
+    }
+
+    // Raw text tags don't have their contents parsed as markup,
+    // so a comment can contain anything other than its own closing tag
+    pub fn is_raw_text(&self) -> bool{
+        matches!(self, Self::Comment)
+    }
+
+    // The geometry used when a tag doesn't have a geometry attribute
+    pub fn default_geometry(&self) -> Geometry{
+        match self{
+            Self::Main => Geometry::Float,
+            _ => Geometry::Custom,
+        }
+    }
+}
+
+// How a tag decides where it is and how big it is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Geometry{
+    // Fills whatever it is inside of
+    Float,
+    // Uses x, y, width and height from its definition
+    Custom,
+}
+
+impl Geometry{
+    pub fn from_name(name: &str) -> Option<Self>{
+        match name.to_ascii_uppercase().as_str(){
+            "FLOAT" => Some(Self::Float),
+            "CUSTOM" => Some(Self::Custom),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttributeValue{
+    // definition="x:100;"
+    Quoted(String),
+    // geometry=FLOAT
+    Unquoted(String),
+    // an attribute with no value at all, ie <square hidden/>
+    Flag,
+}
+
+impl AttributeValue{
+    // The text of the value, regardless of how it was written
+    // Flags have no text, so they give back an empty string
+    pub fn as_str(&self) -> &str{
+        match self{
+            Self::Quoted(value) | Self::Unquoted(value) => value.as_str(),
+            Self::Flag => "",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attribute{
+    pub name: String,
+    pub value: AttributeValue,
+    // where the attribute name starts in the source
+    pub line: usize,
+    pub column: usize,
+    // where the value text starts, after any opening quote
+    pub value_line: usize,
+    pub value_column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node{
+    pub tag: Tag,
+    pub attributes: Vec<Attribute>,
+    // The parsed definition attribute, empty if there wasn't one
+    pub definition: Definition,
+    // The geometry attribute, or the tag's default if there wasn't one
+    pub geometry: Geometry,
+    pub children: Vec<Node>,
+    // Any text between the opening and closing tag
+    // with surrounding whitespace trimmed off
+    pub text: String,
+    // where the opening '<' is in the source
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Node{
+    pub fn attribute(&self, name: &str) -> Option<&Attribute>{
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    pub fn attribute_value(&self, name: &str) -> Option<&str>{
+        self.attribute(name).map(|a| a.value.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document{
+    // Every top level tag in the order they appear
+    pub nodes: Vec<Node>,
+    // The file it was read from, None if it didn't come from one
+    pub path: Option<PathBuf>,
+}
+
+impl Document{
+    // Paths in the markup, like an image's src, start from the document's folder
+    // Documents that didn't come from a file use the working directory
+    pub fn resolve(&self, relative: &str) -> PathBuf{
+        let folder = self.path.as_deref().and_then(Path::parent).unwrap_or(Path::new(""));
+        folder.join(relative)
+    }
+}
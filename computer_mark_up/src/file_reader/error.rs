@@ -0,0 +1,193 @@
+/*
+ * Everything that can go wrong while reading a .cmu file
+ * Errors are built by the tokenizer and parser with just a
+ * line and column, then file_reader fills in the path and
+ * source snippet once it knows them
+ */
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum ReadErrorKind{
+    // The file couldn't be read at all
+    Io(io::Error),
+    // <name> isn't a tag the markup language knows
+    UnknownTag(String),
+    // <name> was opened but never closed
+    UnclosedTag(String),
+    // A broken attribute, ie a missing value or an unterminated quote
+    MalformedAttribute(String),
+    // The definition attribute couldn't be understood
+    BadDefinition(String),
+    // Anything else that is in a place it shouldn't be
+    UnexpectedToken(String),
+}
+
+impl fmt::Display for ReadErrorKind{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Self::Io(error) => write!(f, "couldn't read file: {}", error),
+            Self::UnknownTag(name) => write!(f, "unknown tag <{}>", name),
+            Self::UnclosedTag(name) => write!(f, "<{}> is never closed", name),
+            Self::MalformedAttribute(message) => write!(f, "malformed attribute: {}", message),
+            Self::BadDefinition(message) => write!(f, "bad definition: {}", message),
+            Self::UnexpectedToken(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReadError{
+    pub kind: ReadErrorKind,
+    pub path: String,
+    // 1 based, both are 0 for errors that don't point into the file
+    pub line: usize,
+    pub column: usize,
+    // The offending line with a caret under the column
+    pub snippet: String,
+}
+
+impl ReadError{
+    pub(crate) fn at(kind: ReadErrorKind, line: usize, column: usize) -> Self{
+        Self{
+            kind,
+            path: String::new(),
+            line,
+            column,
+            snippet: String::new()
+        }
+    }
+
+    pub(crate) fn io(error: io::Error, path: &str) -> Self{
+        Self{
+            kind: ReadErrorKind::Io(error),
+            path: String::from(path),
+            line: 0,
+            column: 0,
+            snippet: String::new()
+        }
+    }
+
+    // Attaches the file path and renders the snippet from the source
+    pub(crate) fn with_source(mut self, path: &str, source: &str) -> Self{
+        self.path = String::from(path);
+        self.snippet = render_snippet(source, self.line, self.column);
+        self
+    }
+}
+
+fn render_snippet(source: &str, line: usize, column: usize) -> String{
+    let source_line = match line.checked_sub(1).and_then(|l| source.lines().nth(l)){
+        Some(source_line) => source_line,
+        None => return String::new(),
+    };
+    let gutter = line.to_string();
+    let padding = " ".repeat(gutter.len());
+    // tabs are kept as tabs so the caret lines up however they are displayed
+    let caret_offset: String = source_line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    format!(
+        "{padding} |\n{gutter} | {source_line}\n{padding} | {caret_offset}^"
+    )
+}
+
+impl fmt::Display for ReadError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        writeln!(f, "error: {}", self.kind)?;
+        if self.line == 0{
+            return write!(f, " --> {}", self.path);
+        }
+        write!(f, " --> {}:{}:{}", self.path, self.line, self.column)?;
+        if !self.snippet.is_empty(){
+            write!(f, "\n{}", self.snippet)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ReadError{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>{
+        match &self.kind{
+            ReadErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::file_reader::read_file;
+
+    fn read_error(source: &str) -> ReadError{
+        read_file(source).expect_err("the markup is broken")
+    }
+
+    // The column the caret is drawn under, counting tabs as one
+    fn caret_column(snippet: &str) -> usize{
+        let caret_line = snippet.lines().nth(2).unwrap();
+        let after_gutter = &caret_line[caret_line.find('|').unwrap() + 2..];
+        after_gutter.find('^').unwrap() + 1
+    }
+
+    #[test]
+    fn unclosed_tag(){
+        let error = read_error("<main>\n    <square>\n</main>");
+        assert!(matches!(&error.kind, ReadErrorKind::UnclosedTag(name) if name == "square"));
+        assert_eq!((error.line, error.column), (2, 5));
+        assert_eq!(error.snippet, "  |\n2 |     <square>\n  |     ^");
+    }
+
+    #[test]
+    fn unclosed_at_end_of_file(){
+        let error = read_error("<main>\n<square definition=\"x:1;\"");
+        assert!(matches!(&error.kind, ReadErrorKind::UnclosedTag(name) if name == "square"));
+        assert_eq!((error.line, error.column), (2, 1));
+    }
+
+    #[test]
+    fn mismatched_closing_tag(){
+        let error = read_error("<main><text>hi</square></main>");
+        // the tag that was left open is the one pointed at
+        assert!(matches!(&error.kind, ReadErrorKind::UnclosedTag(name) if name == "text"));
+        assert_eq!((error.line, error.column), (1, 7));
+        assert_eq!(caret_column(&error.snippet), 7);
+    }
+
+    #[test]
+    fn stray_closing_tag(){
+        let error = read_error("<main></main>\n</square>");
+        assert!(matches!(error.kind, ReadErrorKind::UnexpectedToken(_)));
+        assert_eq!((error.line, error.column), (2, 1));
+    }
+
+    #[test]
+    fn unterminated_string(){
+        let error = read_error("<main>\n  <image src=\"a.png/>\n</main>");
+        assert!(matches!(error.kind, ReadErrorKind::MalformedAttribute(_)));
+        // points at the opening quote
+        assert_eq!((error.line, error.column), (2, 14));
+        assert_eq!(caret_column(&error.snippet), 14);
+    }
+
+    #[test]
+    fn tabs_are_kept_so_the_caret_lines_up(){
+        let error = read_error("<main>\n\t\t<nope/>\n</main>");
+        assert!(matches!(&error.kind, ReadErrorKind::UnknownTag(name) if name == "nope"));
+        // a tab counts as one column
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.snippet, "  |\n2 | \t\t<nope/>\n  | \t\t^");
+    }
+
+    #[test]
+    fn display_has_path_position_and_snippet(){
+        let error = read_error("<nope/>");
+        assert_eq!(
+            error.to_string(),
+            "error: unknown tag <nope>\n --> <memory>:1:1\n  |\n1 | <nope/>\n  | ^"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+/*
+ * This reads a file and then
+ * constructs a bunch of components
+ * based off of it.
+ * ezpz lemon squeezy
+*/
+
+mod definition;
+mod document;
+mod error;
+mod parser;
+mod tokenizer;
+
+use std::{fs, path::PathBuf};
+
+pub(crate) use definition::parse_color;
+pub use definition::{Align, Definition, Fit, Overflow, Rgba};
+pub use document::{Document, Geometry, Node, Tag};
+pub use error::{ReadError, ReadErrorKind};
+
+// Used as the path for errors when the markup didn't come from a file
+#[cfg(test)]
+const NO_PATH: &str = "<memory>";
+
+pub fn load_file(file_location: &str) -> Result<String, ReadError>{
+    fs::read_to_string(file_location).map_err(|e| ReadError::io(e, file_location))
+}
+
+// Parses the contents of a .cmu file into a Document
+// The app always reads from a file, only tests have markup that's already in memory
+#[cfg(test)]
+pub fn read_file(file_data: &str) -> Result<Document, ReadError>{
+    parse_source(file_data, NO_PATH)
+}
+
+// Loads and parses a .cmu file, errors will point into that file
+pub fn read_path(file_location: &str) -> Result<Document, ReadError>{
+    let file_data = load_file(file_location)?;
+    let mut document = parse_source(&file_data, file_location)?;
+    document.path = Some(PathBuf::from(file_location));
+    Ok(document)
+}
+
+fn parse_source(file_data: &str, file_location: &str) -> Result<Document, ReadError>{
+    tokenizer::tokenize(file_data)
+        .and_then(parser::parse)
+        .map_err(|e| e.with_source(file_location, file_data))
+}
@@ -0,0 +1,215 @@
+/*
+ * Turns the tokens from the tokenizer into a Document
+ * This is a plain recursive descent parser,
+ * each tag parses its own children
+ */
+
+use super::definition::{self, Definition};
+use super::document::{Attribute, AttributeValue, Document, Geometry, Node, Tag};
+use super::error::{ReadError, ReadErrorKind};
+use super::tokenizer::{Token, TokenKind};
+
+struct Parser{
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+fn unexpected(message: String, token: &Token) -> ReadError{
+    ReadError::at(ReadErrorKind::UnexpectedToken(message), token.line, token.column)
+}
+
+impl Parser{
+    fn peek(&self) -> Option<&Token>{
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token>{
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_document(&mut self) -> Result<Document, ReadError>{
+        let mut nodes = Vec::new();
+        while let Some(token) = self.next(){
+            match &token.kind{
+                TokenKind::OpenTag(name) => nodes.push(self.parse_node(name, token.line, token.column)?),
+                TokenKind::CloseTag(name) => return Err(unexpected(
+                    format!("found </{}> without a matching opening tag", name), &token
+                )),
+                TokenKind::Text(text) => return Err(unexpected(
+                    format!("found text '{}' outside of any tag", text.trim()), &token
+                )),
+                other => return Err(unexpected(format!("unexpected {:?}", other), &token)),
+            }
+        }
+        Ok(Document{ nodes, path: None })
+    }
+
+    // Called once '<name' has been consumed
+    fn parse_node(&mut self, name: &str, line: usize, column: usize) -> Result<Node, ReadError>{
+        let tag = match Tag::from_name(name){
+            Some(tag) => tag,
+            None => return Err(ReadError::at(ReadErrorKind::UnknownTag(String::from(name)), line, column)),
+        };
+        let unclosed = || ReadError::at(ReadErrorKind::UnclosedTag(String::from(name)), line, column);
+        let mut node = Node{
+            tag,
+            attributes: Vec::new(),
+            definition: Definition::default(),
+            geometry: tag.default_geometry(),
+            children: Vec::new(),
+            text: String::new(),
+            line,
+            column
+        };
+
+        // attributes, then either '/>' or '>'
+        let self_closing = loop{
+            let token = self.next().ok_or_else(unclosed)?;
+            match token.kind{
+                TokenKind::SelfClose => break true,
+                TokenKind::TagEnd => break false,
+                TokenKind::AttributeName(attribute_name) => {
+                    let (value, value_line, value_column) =
+                        self.parse_attribute_value(&attribute_name, token.line, token.column)?;
+                    node.attributes.push(Attribute{
+                        name: attribute_name,
+                        value,
+                        line: token.line,
+                        column: token.column,
+                        value_line,
+                        value_column
+                    });
+                },
+                _ => return Err(ReadError::at(
+                    ReadErrorKind::MalformedAttribute(format!("expected an attribute name in <{}>", name)),
+                    token.line,
+                    token.column
+                )),
+            }
+        };
+
+        if let Some(attribute) = node.attribute("definition"){
+            node.definition = definition::parse_definition(
+                attribute.value.as_str(),
+                attribute.value_line,
+                attribute.value_column
+            )?;
+        }
+        if let Some(attribute) = node.attribute("geometry"){
+            node.geometry = match Geometry::from_name(attribute.value.as_str()){
+                Some(geometry) => geometry,
+                None => return Err(ReadError::at(
+                    ReadErrorKind::MalformedAttribute(format!(
+                        "'{}' isn't a geometry, expected FLOAT or CUSTOM", attribute.value.as_str()
+                    )),
+                    attribute.value_line,
+                    attribute.value_column
+                )),
+            };
+        }
+        if self_closing{
+            return Ok(node);
+        }
+
+        // children and text until the matching closing tag
+        loop{
+            let token = self.next().ok_or_else(unclosed)?;
+            match &token.kind{
+                TokenKind::CloseTag(closing) if closing == name => return Ok(node),
+                // A different closing tag means this one was never closed
+                TokenKind::CloseTag(_) => return Err(unclosed()),
+                TokenKind::OpenTag(child) => {
+                    let child = self.parse_node(child, token.line, token.column)?;
+                    node.children.push(child);
+                },
+                TokenKind::Text(text) => {
+                    if !node.text.is_empty(){
+                        node.text.push(' ');
+                    }
+                    node.text.push_str(text.trim());
+                },
+                other => return Err(unexpected(format!("unexpected {:?} in <{}>", other, name), &token)),
+            }
+        }
+    }
+
+    // Called once an attribute name has been consumed
+    // Gives back the value along with the line and column its text starts at
+    fn parse_attribute_value(
+        &mut self,
+        attribute_name: &str,
+        line: usize,
+        column: usize
+    ) -> Result<(AttributeValue, usize, usize), ReadError>{
+        if !matches!(self.peek().map(|t| &t.kind), Some(TokenKind::Equals)){
+            return Ok((AttributeValue::Flag, line, column));
+        }
+        self.next();
+        match self.next(){
+            Some(Token{ kind: TokenKind::AttributeValue(value), line, column }) => {
+                // skip over the opening quote
+                let value_column = match value{
+                    AttributeValue::Quoted(_) => column + 1,
+                    _ => column,
+                };
+                Ok((value, line, value_column))
+            },
+            _ => Err(ReadError::at(
+                ReadErrorKind::MalformedAttribute(format!("expected a value for '{}'", attribute_name)),
+                line,
+                column
+            )),
+        }
+    }
+}
+
+pub(super) fn parse(tokens: Vec<Token>) -> Result<Document, ReadError>{
+    let mut parser = Parser{ tokens, index: 0 };
+    parser.parse_document()
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::file_reader::{read_file, Tag};
+    use super::AttributeValue;
+
+    #[test]
+    fn self_closing_tags_have_no_children(){
+        let document = read_file("<main><square/><text>hi</text></main>").unwrap();
+        let main = &document.nodes[0];
+        assert_eq!(main.tag, Tag::Main);
+        assert_eq!(main.children.len(), 2);
+        assert_eq!(main.children[0].tag, Tag::Square);
+        assert!(main.children[0].children.is_empty());
+        assert_eq!(main.children[1].text, "hi");
+    }
+
+    #[test]
+    fn attribute_values(){
+        let document = read_file(r#"<image src="images/a.png" geometry=FLOAT hidden/>"#).unwrap();
+        let image = &document.nodes[0];
+        let value = |name| &image.attribute(name).unwrap().value;
+        assert_eq!(value("src"), &AttributeValue::Quoted(String::from("images/a.png")));
+        assert_eq!(value("geometry"), &AttributeValue::Unquoted(String::from("FLOAT")));
+        assert_eq!(value("hidden"), &AttributeValue::Flag);
+        assert_eq!(image.attribute_value("src"), Some("images/a.png"));
+    }
+
+    #[test]
+    fn quoted_and_unquoted_values_mean_the_same(){
+        let quoted = read_file(r#"<square geometry="FLOAT"/>"#).unwrap();
+        let unquoted = read_file("<square geometry=FLOAT/>").unwrap();
+        assert_eq!(quoted.nodes[0].geometry, unquoted.nodes[0].geometry);
+    }
+
+    #[test]
+    fn comments_keep_markup_as_text(){
+        let document = read_file("<main><comment><square/> isn't a tag</comment></main>").unwrap();
+        let comment = &document.nodes[0].children[0];
+        assert_eq!(comment.tag, Tag::Comment);
+        assert!(comment.children.is_empty());
+        assert_eq!(comment.text, "<square/> isn't a tag");
+    }
+}
@@ -0,0 +1,309 @@
+/*
+ * Splits the raw text of a .cmu file into tokens
+ * The tokenizer has two modes:
+ * - Content: everything up to the next '<' is text
+ * - Tag: names, '=', values and the closing '>' or '/>'
+ */
+
+use super::document::{AttributeValue, Tag};
+use super::error::{ReadError, ReadErrorKind};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum TokenKind{
+    // '<name'
+    OpenTag(String),
+    // '</name>'
+    CloseTag(String),
+    // '>'
+    TagEnd,
+    // '/>'
+    SelfClose,
+    AttributeName(String),
+    Equals,
+    AttributeValue(AttributeValue),
+    // Text between tags, never whitespace only
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Token{
+    pub kind: TokenKind,
+    // 1 based, pointing at the first character of the token
+    pub line: usize,
+    pub column: usize,
+}
+
+// Walks over the source one character at a time
+// while keeping track of where it is
+pub(super) struct Cursor{
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Cursor{
+    pub(super) fn new(source: &str) -> Self{
+        Self::starting_at(source, 1, 1)
+    }
+
+    // For text that was cut out of a bigger file,
+    // so positions still point into the original file
+    pub(super) fn starting_at(source: &str, line: usize, column: usize) -> Self{
+        Self{
+            chars: source.chars().collect(),
+            index: 0,
+            line,
+            column
+        }
+    }
+
+    pub(super) fn peek(&self) -> Option<char>{
+        self.chars.get(self.index).copied()
+    }
+
+    pub(super) fn peek_second(&self) -> Option<char>{
+        self.chars.get(self.index + 1).copied()
+    }
+
+    pub(super) fn starts_with(&self, pattern: &str) -> bool{
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.index + offset) == Some(&c))
+    }
+
+    pub(super) fn bump(&mut self) -> Option<char>{
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n'{
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    pub(super) fn skip_whitespace(&mut self){
+        while self.peek().is_some_and(char::is_whitespace){
+            self.bump();
+        }
+    }
+
+    pub(super) fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> String{
+        let mut taken = String::new();
+        while let Some(c) = self.peek(){
+            if !predicate(c){
+                break;
+            }
+            taken.push(c);
+            self.bump();
+        }
+        taken
+    }
+
+    pub(super) fn position(&self) -> (usize, usize){
+        (self.line, self.column)
+    }
+}
+
+fn is_name_char(c: char) -> bool{
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+// Unquoted values run until whitespace or the end of the tag
+fn is_unquoted_value_char(c: char) -> bool{
+    !c.is_whitespace() && c != '>' && c != '/' && c != '"' && c != '\''
+}
+
+pub(super) fn tokenize(source: &str) -> Result<Vec<Token>, ReadError>{
+    let mut cursor = Cursor::new(source);
+    let mut tokens = Vec::new();
+
+    while cursor.peek().is_some(){
+        let (line, column) = cursor.position();
+
+        if cursor.starts_with("</"){
+            cursor.bump();
+            cursor.bump();
+            cursor.skip_whitespace();
+            let name = cursor.take_while(is_name_char);
+            cursor.skip_whitespace();
+            if cursor.bump() != Some('>'){
+                return Err(ReadError::at(
+                    ReadErrorKind::UnexpectedToken(format!("expected '>' to close </{}>", name)), line, column
+                ));
+            }
+            tokens.push(Token{ kind: TokenKind::CloseTag(name), line, column });
+        } else if cursor.peek() == Some('<'){
+            cursor.bump();
+            let name = cursor.take_while(is_name_char);
+            if name.is_empty(){
+                return Err(ReadError::at(
+                    ReadErrorKind::UnexpectedToken(String::from("expected a tag name after '<'")), line, column
+                ));
+            }
+            tokens.push(Token{ kind: TokenKind::OpenTag(name.clone()), line, column });
+            let self_closing = tokenize_tag_body(&mut cursor, &mut tokens, &name, line, column)?;
+
+            // Raw text tags swallow everything up to their closing tag
+            let raw = Tag::from_name(&name).is_some_and(|t| t.is_raw_text());
+            if raw && !self_closing{
+                let (line, column) = cursor.position();
+                let closing = format!("</{}", name);
+                let mut text = String::new();
+                while cursor.peek().is_some() && !cursor.starts_with(&closing){
+                    text.push(cursor.bump().unwrap());
+                }
+                push_text(&mut tokens, text, line, column);
+            }
+        } else {
+            let text = cursor.take_while(|c| c != '<');
+            push_text(&mut tokens, text, line, column);
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn push_text(tokens: &mut Vec<Token>, text: String, line: usize, column: usize){
+    if !text.trim().is_empty(){
+        tokens.push(Token{ kind: TokenKind::Text(text), line, column });
+    }
+}
+
+// Reads everything after '<name' up to and including '>' or '/>'
+// Returns true if the tag closed itself
+fn tokenize_tag_body(
+    cursor: &mut Cursor,
+    tokens: &mut Vec<Token>,
+    tag_name: &str,
+    tag_line: usize,
+    tag_column: usize
+) -> Result<bool, ReadError>{
+    loop{
+        cursor.skip_whitespace();
+        let (line, column) = cursor.position();
+        match cursor.peek(){
+            None => return Err(ReadError::at(
+                ReadErrorKind::UnclosedTag(String::from(tag_name)), tag_line, tag_column
+            )),
+            Some('>') => {
+                cursor.bump();
+                tokens.push(Token{ kind: TokenKind::TagEnd, line, column });
+                return Ok(false);
+            },
+            Some('/') if cursor.peek_second() == Some('>') => {
+                cursor.bump();
+                cursor.bump();
+                tokens.push(Token{ kind: TokenKind::SelfClose, line, column });
+                return Ok(true);
+            },
+            Some('=') => {
+                cursor.bump();
+                tokens.push(Token{ kind: TokenKind::Equals, line, column });
+                cursor.skip_whitespace();
+                let value = tokenize_attribute_value(cursor)?;
+                tokens.push(value);
+            },
+            Some(c) if is_name_char(c) => {
+                let name = cursor.take_while(is_name_char);
+                tokens.push(Token{ kind: TokenKind::AttributeName(name), line, column });
+            },
+            Some(c) => return Err(ReadError::at(
+                ReadErrorKind::MalformedAttribute(format!("unexpected character '{}' in <{}>", c, tag_name)), line, column
+            )),
+        }
+    }
+}
+
+fn tokenize_attribute_value(cursor: &mut Cursor) -> Result<Token, ReadError>{
+    let (line, column) = cursor.position();
+    match cursor.peek(){
+        Some(quote) if quote == '"' || quote == '\'' => {
+            cursor.bump();
+            let value = cursor.take_while(|c| c != quote);
+            if cursor.bump() != Some(quote){
+                return Err(ReadError::at(
+                    ReadErrorKind::MalformedAttribute(format!("missing closing {} on value", quote)), line, column
+                ));
+            }
+            Ok(Token{ kind: TokenKind::AttributeValue(AttributeValue::Quoted(value)), line, column })
+        },
+        _ => {
+            let value = cursor.take_while(is_unquoted_value_char);
+            if value.is_empty(){
+                return Err(ReadError::at(
+                    ReadErrorKind::MalformedAttribute(String::from("expected a value after '='")), line, column
+                ));
+            }
+            Ok(Token{ kind: TokenKind::AttributeValue(AttributeValue::Unquoted(value)), line, column })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind>{
+        tokenize(source).unwrap().into_iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn self_closing_tag(){
+        assert_eq!(kinds("<square/>"), vec![
+            TokenKind::OpenTag(String::from("square")),
+            TokenKind::SelfClose,
+        ]);
+        // whitespace before the '/>' is fine too
+        assert_eq!(kinds("<square  />"), kinds("<square/>"));
+    }
+
+    #[test]
+    fn quoted_and_unquoted_values(){
+        assert_eq!(kinds(r#"<image src="a b.png" fit='cover' geometry=FLOAT>"#), vec![
+            TokenKind::OpenTag(String::from("image")),
+            TokenKind::AttributeName(String::from("src")),
+            TokenKind::Equals,
+            TokenKind::AttributeValue(AttributeValue::Quoted(String::from("a b.png"))),
+            TokenKind::AttributeName(String::from("fit")),
+            TokenKind::Equals,
+            TokenKind::AttributeValue(AttributeValue::Quoted(String::from("cover"))),
+            TokenKind::AttributeName(String::from("geometry")),
+            TokenKind::Equals,
+            TokenKind::AttributeValue(AttributeValue::Unquoted(String::from("FLOAT"))),
+            TokenKind::TagEnd,
+        ]);
+    }
+
+    #[test]
+    fn unquoted_value_stops_at_self_close(){
+        assert_eq!(kinds("<square geometry=FLOAT/>"), vec![
+            TokenKind::OpenTag(String::from("square")),
+            TokenKind::AttributeName(String::from("geometry")),
+            TokenKind::Equals,
+            TokenKind::AttributeValue(AttributeValue::Unquoted(String::from("FLOAT"))),
+            TokenKind::SelfClose,
+        ]);
+    }
+
+    #[test]
+    fn comment_text_is_raw(){
+        assert_eq!(kinds("<comment> <square/> a < b </comment>"), vec![
+            TokenKind::OpenTag(String::from("comment")),
+            TokenKind::TagEnd,
+            TokenKind::Text(String::from(" <square/> a < b ")),
+            TokenKind::CloseTag(String::from("comment")),
+        ]);
+    }
+
+    #[test]
+    fn positions_are_one_based(){
+        let tokens = tokenize("<main>\n  <square/>\n</main>").unwrap();
+        let square = &tokens[2];
+        assert_eq!(square.kind, TokenKind::OpenTag(String::from("square")));
+        assert_eq!((square.line, square.column), (2, 3));
+    }
+}
@@ -1,5 +1,10 @@
-use winit::event::{WindowEvent, VirtualKeyCode, KeyboardInput};
-use hashbrown::HashMap;
+use winit::event::{
+    WindowEvent, VirtualKeyCode, KeyboardInput,
+    ElementState, MouseButton, MouseScrollDelta, ModifiersState, Ime
+};
+use std::time::{Duration, Instant};
+
+use hashbrown::{HashMap, HashSet};
 
 // Every single key on the keyboard that is handled by wgpu
 pub(crate) const VIRTUALKEYS: [VirtualKeyCode; 134] = [
@@ -148,10 +153,46 @@ fn create_keyboard_hash_map() -> HashMap<VirtualKeyCode, bool>{
     keys
 }
 
+// Where each button is kept in InputHandler.mouse_buttons
+// Any extra buttons a mouse has are ignored
+fn mouse_button_index(button: MouseButton) -> Option<usize>{
+    match button{
+        MouseButton::Left => Some(0),
+        MouseButton::Right => Some(1),
+        MouseButton::Middle => Some(2),
+        MouseButton::Other(_) => None,
+    }
+}
+
 pub struct InputHandler{
     key_presses: HashMap<VirtualKeyCode, bool>, 
     mouse_position: [f32; 2],
-    mouse_buttons: [bool; 3]
+    // Keys that went down or up since the last advance_frame
+    keys_just_pressed: HashSet<VirtualKeyCode>,
+    keys_just_released: HashSet<VirtualKeyCode>,
+    // When each held key went down
+    key_press_started: HashMap<VirtualKeyCode, Instant>,
+    // left, right, middle
+    mouse_buttons: [bool; 3],
+    mouse_just_pressed: [bool; 3],
+    mouse_just_released: [bool; 3],
+    mouse_press_started: [Option<Instant>; 3],
+    // How far the wheel moved since the last advance_frame
+    // Mice usually scroll in lines and touchpads in pixels
+    scroll_lines: [f32; 2],
+    scroll_pixels: [f32; 2],
+    modifiers: ModifiersState,
+    // Text typed since the last advance_frame, from plain key presses
+    // and from whatever an input method committed
+    text_input: String,
+    // What an input method is composing but hasn't committed yet
+    // and the byte range of its cursor, if it shows one
+    preedit: String,
+    preedit_cursor: Option<(usize, usize)>,
+    // Committed text that hasn't come back as ReceivedCharacter yet
+    // Some platforms send both, and it should only be typed once
+    ime_echo: String,
+    ime_enabled: bool
 }
 
 impl InputHandler{
@@ -159,7 +200,21 @@ impl InputHandler{
         Self{
             key_presses: create_keyboard_hash_map(),
             mouse_position: [0.0, 0.0],
-            mouse_buttons: [false, false, false]
+            keys_just_pressed: HashSet::new(),
+            keys_just_released: HashSet::new(),
+            key_press_started: HashMap::new(),
+            mouse_buttons: [false, false, false],
+            mouse_just_pressed: [false, false, false],
+            mouse_just_released: [false, false, false],
+            mouse_press_started: [None, None, None],
+            scroll_lines: [0.0, 0.0],
+            scroll_pixels: [0.0, 0.0],
+            modifiers: ModifiersState::empty(),
+            text_input: String::new(),
+            preedit: String::new(),
+            preedit_cursor: None,
+            ime_echo: String::new(),
+            ime_enabled: false
         }
     }
 
@@ -169,22 +224,189 @@ impl InputHandler{
                 self.mouse_position = [position.x as f32, position.y as f32];
                 true
             },
-            WindowEvent::KeyboardInput { input: KeyboardInput{virtual_keycode, state, ..}, .. } => {
-                // state says whether the key was pressed or released
-                // by using *state==winit::event::ElementState::Pressed,
-                // the value will be set to false once it is released 
-                // and will stay true as long as it is held
-                self.key_presses.insert(virtual_keycode.unwrap(), *state==winit::event::ElementState::Pressed);
+            WindowEvent::KeyboardInput { input: KeyboardInput{virtual_keycode, state, scancode, ..}, .. } => {
+                // some keys, especially on non US layouts, don't map to a
+                // VirtualKeyCode at all, there's nothing to track for those
+                // but their text still comes through ReceivedCharacter
+                match virtual_keycode{
+                    Some(key) => self.set_key(*key, *state==ElementState::Pressed),
+                    None => log::debug!("ignoring key with scancode {} and no virtual keycode", scancode),
+                }
                 true
-            }
+            },
+            WindowEvent::ReceivedCharacter(c) => {
+                // the input method is still composing, what it's
+                // showing is in preedit until it commits
+                if !self.preedit.is_empty(){
+                    return true;
+                }
+                // already typed by the commit it came from
+                if let Some(rest) = self.ime_echo.strip_prefix(*c){
+                    self.ime_echo = String::from(rest);
+                    return true;
+                }
+                self.ime_echo.clear();
+                // backspace, enter, escape and friends show up here too,
+                // they're better read as key presses
+                if !c.is_control(){
+                    self.text_input.push(*c);
+                }
+                true
+            },
+            WindowEvent::Ime(ime) => {
+                match ime{
+                    Ime::Enabled => self.ime_enabled = true,
+                    Ime::Preedit(text, cursor) => {
+                        self.preedit.clone_from(text);
+                        self.preedit_cursor = *cursor;
+                    },
+                    Ime::Commit(text) => {
+                        self.text_input.push_str(text);
+                        self.ime_echo.clone_from(text);
+                        self.preedit.clear();
+                        self.preedit_cursor = None;
+                    },
+                    Ime::Disabled => {
+                        self.ime_enabled = false;
+                        self.preedit.clear();
+                        self.preedit_cursor = None;
+                    },
+                }
+                true
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let Some(index) = mouse_button_index(*button){
+                    self.set_mouse_button(index, *state==ElementState::Pressed);
+                }
+                true
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                // winit gives which way the content moves, so turning the wheel
+                // away from you is positive. It's flipped here to be which way
+                // the view moves instead, the same way SceneState.scroll_to goes
+                match delta{
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        self.scroll_lines[0] -= x;
+                        self.scroll_lines[1] -= y;
+                    },
+                    MouseScrollDelta::PixelDelta(position) => {
+                        self.scroll_pixels[0] -= position.x as f32;
+                        self.scroll_pixels[1] -= position.y as f32;
+                    },
+                }
+                true
+            },
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                true
+            },
             _ => false
         }
     }
 
+    fn set_key(&mut self, key: VirtualKeyCode, pressed: bool){
+        // pressed says whether the key is down or up
+        // the value will be set to false once it is released
+        // and will stay true as long as it is held
+        let was_pressed = self.key_presses.insert(key, pressed).unwrap_or(false);
+        // holding a key down repeats the pressed event,
+        // only the first one counts as a new press
+        if pressed && !was_pressed{
+            self.keys_just_pressed.insert(key);
+            self.key_press_started.insert(key, Instant::now());
+        } else if !pressed && was_pressed{
+            self.keys_just_released.insert(key);
+            self.key_press_started.remove(&key);
+        }
+    }
+
+    fn set_mouse_button(&mut self, index: usize, pressed: bool){
+        let was_pressed = self.mouse_buttons[index];
+        self.mouse_buttons[index] = pressed;
+        if pressed && !was_pressed{
+            self.mouse_just_pressed[index] = true;
+            self.mouse_press_started[index] = Some(Instant::now());
+        } else if !pressed && was_pressed{
+            self.mouse_just_released[index] = true;
+            self.mouse_press_started[index] = None;
+        }
+    }
+
+    // Called once a frame after everything has updated
+    // Forgets which keys and buttons were just pressed or released,
+    // how far the wheel scrolled and what was typed
+    pub fn advance_frame(&mut self){
+        self.keys_just_pressed.clear();
+        self.keys_just_released.clear();
+        self.mouse_just_pressed = [false, false, false];
+        self.mouse_just_released = [false, false, false];
+        self.scroll_lines = [0.0, 0.0];
+        self.scroll_pixels = [0.0, 0.0];
+        self.text_input.clear();
+        // echoes come in the same batch of events as the commit,
+        // anything typed after that is new
+        self.ime_echo.clear();
+    }
+
     pub fn get_mouse_pos(&self) -> [f32; 2]{
         self.mouse_position
     }
 
+    // Whether a mouse button is held down
+    // always false for buttons past the middle one
+    pub fn get_mouse_button(&self, button: MouseButton) -> bool{
+        mouse_button_index(button).is_some_and(|index| self.mouse_buttons[index])
+    }
+
+    // Whether the button went down this frame
+    pub fn mouse_button_just_pressed(&self, button: MouseButton) -> bool{
+        mouse_button_index(button).is_some_and(|index| self.mouse_just_pressed[index])
+    }
+
+    // Whether the button came up this frame
+    pub fn mouse_button_just_released(&self, button: MouseButton) -> bool{
+        mouse_button_index(button).is_some_and(|index| self.mouse_just_released[index])
+    }
+
+    // How long the button has been held, None if it isn't
+    pub fn mouse_button_press_duration(&self, button: MouseButton) -> Option<Duration>{
+        let started = self.mouse_press_started[mouse_button_index(button)?]?;
+        Some(started.elapsed())
+    }
+
+    // Positive values scroll right and down, ie further into the document
+    pub fn get_scroll_lines(&self) -> [f32; 2]{
+        self.scroll_lines
+    }
+
+    // The same as get_scroll_lines, for touchpads and anything else that scrolls smoothly
+    pub fn get_scroll_pixels(&self) -> [f32; 2]{
+        self.scroll_pixels
+    }
+
+    // Everything typed this frame, in order
+    pub fn get_text_input(&self) -> &str{
+        &self.text_input
+    }
+
+    // The text an input method is still composing, None when it isn't
+    // The cursor is a byte range into the text, None when it's hidden
+    pub fn get_preedit(&self) -> Option<(&str, Option<(usize, usize)>)>{
+        if self.preedit.is_empty(){
+            return None;
+        }
+        Some((&self.preedit, self.preedit_cursor))
+    }
+
+    pub fn ime_enabled(&self) -> bool{
+        self.ime_enabled
+    }
+
+    // Which of shift, ctrl, alt and logo (super) are held
+    pub fn get_modifiers(&self) -> ModifiersState{
+        self.modifiers
+    }
+
     // Get whether a specific key was pressed or not
     pub fn get_key_press(&self, key: &VirtualKeyCode) -> bool{
         // Try to return the value from the hashmap
@@ -192,6 +414,21 @@ impl InputHandler{
         *self.key_presses.get(key).unwrap_or(&false)
     }
 
+    // Whether the key went down this frame, key repeats don't count
+    pub fn key_just_pressed(&self, key: &VirtualKeyCode) -> bool{
+        self.keys_just_pressed.contains(key)
+    }
+
+    // Whether the key came up this frame
+    pub fn key_just_released(&self, key: &VirtualKeyCode) -> bool{
+        self.keys_just_released.contains(key)
+    }
+
+    // How long the key has been held, None if it isn't
+    pub fn key_press_duration(&self, key: &VirtualKeyCode) -> Option<Duration>{
+        self.key_press_started.get(key).map(Instant::elapsed)
+    }
+
     // Returns a list of all pressed keys
     // Not fast, shouldn't be called a lot
     pub fn get_keys_pressed(&self) -> Vec<VirtualKeyCode>{
@@ -204,4 +441,203 @@ impl InputHandler{
             // turn into a Vec
             .collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests{
+    use winit::{dpi::PhysicalPosition, event::{DeviceId, TouchPhase}};
+
+    use super::*;
+
+    #[allow(deprecated)]
+    fn wheel(delta: MouseScrollDelta) -> WindowEvent<'static>{
+        WindowEvent::MouseWheel{
+            // never looked at
+            device_id: unsafe { DeviceId::dummy() },
+            delta,
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::empty()
+        }
+    }
+
+    #[allow(deprecated)]
+    fn keyboard(scancode: u32, virtual_keycode: Option<VirtualKeyCode>, state: ElementState) -> WindowEvent<'static>{
+        WindowEvent::KeyboardInput{
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput{
+                scancode,
+                state,
+                virtual_keycode,
+                modifiers: ModifiersState::empty()
+            },
+            is_synthetic: false
+        }
+    }
+
+    fn key(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static>{
+        keyboard(0, Some(key), state)
+    }
+
+    fn type_text(input: &mut InputHandler, text: &str){
+        for c in text.chars(){
+            input.handle_window_event(&WindowEvent::ReceivedCharacter(c));
+        }
+    }
+
+    fn ime(input: &mut InputHandler, event: Ime){
+        input.handle_window_event(&WindowEvent::Ime(event));
+    }
+
+    #[allow(deprecated)]
+    fn button(button: MouseButton, state: ElementState) -> WindowEvent<'static>{
+        WindowEvent::MouseInput{
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button,
+            modifiers: ModifiersState::empty()
+        }
+    }
+
+    #[test]
+    fn key_repeat_isnt_a_new_press(){
+        let mut input = InputHandler::new_default();
+        input.handle_window_event(&key(VirtualKeyCode::A, ElementState::Pressed));
+        assert!(input.key_just_pressed(&VirtualKeyCode::A));
+        assert!(input.get_key_press(&VirtualKeyCode::A));
+        let started = input.key_press_started[&VirtualKeyCode::A];
+
+        input.advance_frame();
+        // the OS repeats Pressed for as long as the key is held
+        input.handle_window_event(&key(VirtualKeyCode::A, ElementState::Pressed));
+        input.handle_window_event(&key(VirtualKeyCode::A, ElementState::Pressed));
+        assert!(!input.key_just_pressed(&VirtualKeyCode::A));
+        assert!(input.get_key_press(&VirtualKeyCode::A));
+        // and the press still started at the first one
+        assert_eq!(input.key_press_started[&VirtualKeyCode::A], started);
+    }
+
+    #[test]
+    fn release_fires_once(){
+        let mut input = InputHandler::new_default();
+        // releasing a key that was never pressed, ie held since before the window opened
+        input.handle_window_event(&key(VirtualKeyCode::B, ElementState::Released));
+        assert!(!input.key_just_released(&VirtualKeyCode::B));
+
+        input.handle_window_event(&key(VirtualKeyCode::B, ElementState::Pressed));
+        input.advance_frame();
+        input.handle_window_event(&key(VirtualKeyCode::B, ElementState::Released));
+        assert!(input.key_just_released(&VirtualKeyCode::B));
+        assert!(!input.get_key_press(&VirtualKeyCode::B));
+        assert_eq!(input.key_press_duration(&VirtualKeyCode::B), None);
+
+        input.advance_frame();
+        input.handle_window_event(&key(VirtualKeyCode::B, ElementState::Released));
+        assert!(!input.key_just_released(&VirtualKeyCode::B));
+    }
+
+    #[test]
+    fn advance_frame_clears_edges(){
+        let mut input = InputHandler::new_default();
+        input.handle_window_event(&key(VirtualKeyCode::C, ElementState::Pressed));
+        input.handle_window_event(&key(VirtualKeyCode::D, ElementState::Pressed));
+        input.handle_window_event(&key(VirtualKeyCode::D, ElementState::Released));
+        input.handle_window_event(&button(MouseButton::Left, ElementState::Pressed));
+        input.handle_window_event(&button(MouseButton::Right, ElementState::Pressed));
+        input.handle_window_event(&button(MouseButton::Right, ElementState::Released));
+        // pressed and released in the same frame counts as both
+        assert!(input.key_just_pressed(&VirtualKeyCode::D));
+        assert!(input.key_just_released(&VirtualKeyCode::D));
+        assert!(input.mouse_button_just_pressed(MouseButton::Left));
+        assert!(input.mouse_button_just_released(MouseButton::Right));
+
+        input.advance_frame();
+        assert!(!input.key_just_pressed(&VirtualKeyCode::C));
+        assert!(!input.key_just_pressed(&VirtualKeyCode::D));
+        assert!(!input.key_just_released(&VirtualKeyCode::D));
+        assert!(!input.mouse_button_just_pressed(MouseButton::Left));
+        assert!(!input.mouse_button_just_released(MouseButton::Right));
+        // held things stay held
+        assert!(input.get_key_press(&VirtualKeyCode::C));
+        assert!(input.get_mouse_button(MouseButton::Left));
+    }
+
+    #[test]
+    fn wheel_away_from_you_scrolls_up(){
+        let mut input = InputHandler::new_default();
+        // what winit sends for one notch of the wheel away from you
+        input.handle_window_event(&wheel(MouseScrollDelta::LineDelta(0.0, 1.0)));
+        assert_eq!(input.get_scroll_lines(), [0.0, -1.0]);
+        input.handle_window_event(&wheel(MouseScrollDelta::LineDelta(-2.0, -3.0)));
+        assert_eq!(input.get_scroll_lines(), [2.0, 2.0]);
+
+        // natural scrolling on a touchpad, fingers moving up
+        input.handle_window_event(&wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, -40.0))));
+        assert_eq!(input.get_scroll_pixels(), [0.0, 40.0]);
+
+        input.advance_frame();
+        assert_eq!(input.get_scroll_lines(), [0.0, 0.0]);
+        assert_eq!(input.get_scroll_pixels(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn typed_characters(){
+        let mut input = InputHandler::new_default();
+        type_text(&mut input, "hé\u{8}\r!");
+        // backspace and enter are keys, not text
+        assert_eq!(input.get_text_input(), "hé!");
+        input.advance_frame();
+        assert_eq!(input.get_text_input(), "");
+    }
+
+    #[test]
+    fn keys_without_a_keycode_are_ignored(){
+        let mut input = InputHandler::new_default();
+        // ie a dead key on a non US layout
+        assert!(input.handle_window_event(&keyboard(40, None, ElementState::Pressed)));
+        assert!(input.handle_window_event(&keyboard(40, None, ElementState::Released)));
+        assert!(input.get_keys_pressed().is_empty());
+        // whatever it made still gets typed
+        type_text(&mut input, "ê");
+        assert_eq!(input.get_text_input(), "ê");
+    }
+
+    #[test]
+    fn preedit_stays_out_of_the_text_until_it_commits(){
+        let mut input = InputHandler::new_default();
+        ime(&mut input, Ime::Enabled);
+        assert!(input.ime_enabled());
+        assert_eq!(input.get_preedit(), None);
+
+        ime(&mut input, Ime::Preedit(String::from("ni"), Some((2, 2))));
+        // the platform might still send the keys it's composing from
+        type_text(&mut input, "i");
+        assert_eq!(input.get_preedit(), Some(("ni", Some((2, 2)))));
+        assert_eq!(input.get_text_input(), "");
+
+        ime(&mut input, Ime::Preedit(String::new(), None));
+        ime(&mut input, Ime::Commit(String::from("你")));
+        assert_eq!(input.get_preedit(), None);
+        assert_eq!(input.get_text_input(), "你");
+
+        ime(&mut input, Ime::Disabled);
+        assert!(!input.ime_enabled());
+    }
+
+    #[test]
+    fn commits_sent_again_as_characters_are_typed_once(){
+        let mut input = InputHandler::new_default();
+        ime(&mut input, Ime::Commit(String::from("你好")));
+        type_text(&mut input, "你好");
+        assert_eq!(input.get_text_input(), "你好");
+
+        // something else after the echo is new text
+        type_text(&mut input, "a");
+        assert_eq!(input.get_text_input(), "你好a");
+
+        // and so is the same text in a later frame
+        ime(&mut input, Ime::Commit(String::from("b")));
+        input.advance_frame();
+        type_text(&mut input, "b");
+        assert_eq!(input.get_text_input(), "b");
+    }
+}
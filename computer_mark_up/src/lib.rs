@@ -0,0 +1,19 @@
+/*
+ * Everything a component is written against, the scene tree,
+ * input, update commands and rendering, lives in the library
+ * main.rs is the app that opens .cmu files with it
+ */
+
+pub mod render;
+pub mod collision;
+
+pub mod components;
+pub mod update_commands;
+pub mod input_handler;
+pub mod scene_state;
+
+pub mod file_reader;
+pub mod cli;
+
+#[cfg(test)]
+mod snapshot_tests;
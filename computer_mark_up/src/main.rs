@@ -1,18 +1,19 @@
+
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
+    dpi::PhysicalSize,
 };
 
-mod render;
-mod collision;
-
-mod components;
-mod update_commands;
-mod input_handler;
-mod scene_state;
+use std::path::{Path, PathBuf};
 
-mod file_reader;
+use computer_mark_up::{
+    cli, file_reader, input_handler, render,
+    render::{backend::RenderBackend, window_state::WindowState},
+    scene_state::SceneState,
+    update_commands::UpdateCommand
+};
 
 /*
  * There is a WindowState and a SceneState
@@ -22,27 +23,98 @@ mod file_reader;
  * - Component Management
  */
 
-async fn run() {
+// The file name, or the whole path if there isn't one
+fn default_title(path: &Path) -> String{
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Carries out what the components asked for during an update
+// document_path is the file being shown, navigating changes it
+// Returns false once the app should quit
+fn execute_commands(commands: Vec<UpdateCommand>, ws: &mut WindowState, ss: &mut SceneState, document_path: &mut PathBuf, fixed_title: bool) -> bool{
+    for command in commands{
+        match command{
+            UpdateCommand::Void => {},
+            UpdateCommand::Quit => return false,
+            UpdateCommand::SetWindowTitle(title) => ws.window().set_title(&title),
+            UpdateCommand::RequestRedraw => ss.mark_dirty(),
+            UpdateCommand::Navigate(target) => {
+                let target = document_path.parent().unwrap_or(Path::new("")).join(target);
+                // a broken link leaves the current document up
+                match file_reader::read_path(&target.to_string_lossy()){
+                    Ok(document) => {
+                        *ss = SceneState::from_document(&document, ws);
+                        if !fixed_title{
+                            ws.window().set_title(&default_title(&target));
+                        }
+                        *document_path = target;
+                        // nothing after this was meant for the new document
+                        return true;
+                    },
+                    Err(e) => eprintln!("{}", e),
+                }
+            },
+            UpdateCommand::ScrollTo(position) => ss.scroll_to(position),
+            UpdateCommand::Focus(id) => ss.focus(Some(id)),
+            UpdateCommand::Unfocus => ss.focus(None),
+            UpdateCommand::Spawn{ parent, subtree } => {
+                if ss.insert_subtree(parent, subtree).is_none(){
+                    log::warn!("couldn't spawn a component under {:?}, it isn't in the scene", parent);
+                }
+            },
+            UpdateCommand::Remove(id) => { ss.remove_subtree(id); },
+            UpdateCommand::SetCursorIcon(icon) => ws.window().set_cursor_icon(icon),
+            // SceneState.update turns these into Focus and Remove
+            UpdateCommand::FocusSelf | UpdateCommand::RemoveSelf => {},
+        }
+    }
+    true
+}
+
+async fn run(arguments: cli::Arguments) {
     env_logger::init();
+
+    // Read the document before opening a window so mistakes
+    // in it don't flash an empty window up
+    let document = match file_reader::read_path(&arguments.file){
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let mut ws = render::window_state::WindowState::new(window).await;
+    let mut document_path = PathBuf::from(&arguments.file);
+    // a title from the command line stays put when navigating
+    let fixed_title = arguments.title.is_some();
+    let title = arguments.title.unwrap_or_else(|| default_title(&document_path));
+    let mut window_builder = WindowBuilder::new().with_title(title);
+    if arguments.width.is_some() || arguments.height.is_some(){
+        // fill in whichever one wasn't given with winit's default
+        window_builder = window_builder.with_inner_size(PhysicalSize::new(
+            arguments.width.unwrap_or(800),
+            arguments.height.unwrap_or(600)
+        ));
+    }
+    let window = window_builder.build(&event_loop).unwrap();
+    let mut ws = WindowState::new(window).await;
+    if let Some(background) = arguments.background{
+        ws.set_clear_color(background);
+    }
+    // lets people type with an input method, ie for chinese or japanese
+    ws.window().set_ime_allowed(true);
     let mut ih = input_handler::InputHandler::new_default();
-    let mut ss = scene_state::SceneState::new_empty();
 
-    // Create textures
-    ws.load_color(render::color::Color::new([1.0, 0.5, 1.0, 1.0]));
-    ws.load_color(render::color::Color::new([0.0, 1.0, 1.0, 1.0]));
-
-    // Create components
-    ss.add_component(
-        components::SquareComponent::new([0.0, 0.0], 800.0, 600.0)
-    );
-    ss.add_component(
-        components::HoverComponent::new([800.0, 0.0], 800.0, 600.0, 0, 1)
-    );
+    // Creates the components and loads their textures
+    let mut ss = SceneState::from_document(&document, &mut ws);
 
     event_loop.run(move |event, _, control_flow| match event {
+        // Sleep until something happens rather than drawing over and over
+        Event::NewEvents(StartCause::Init) => *control_flow = ControlFlow::Wait,
         Event::WindowEvent {
             ref event,
             window_id,
@@ -50,29 +122,115 @@ async fn run() {
             WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
             WindowEvent::Resized(physical_size) => {
                 ws.resize(*physical_size);
+                // FLOAT tags fill the window, whatever size it is now
+                ss.relayout(ws.target_size().map(|pixels| pixels as f32));
             }
             WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                 // new_inner_size is &&mut so we have to dereference it twice
                 ws.resize(**new_inner_size);
+                // FLOAT tags fill the window, whatever size it is now
+                ss.relayout(ws.target_size().map(|pixels| pixels as f32));
             }
             _ => {}
         },
-        Event::MainEventsCleared => ws.window().request_redraw(),
+        // Every batch of events gets one update,
+        // and a redraw only if it changed how anything looks
+        Event::MainEventsCleared => {
+            // the window was closed, nothing else matters
+            if *control_flow == ControlFlow::Exit{
+                return;
+            }
+            let commands = ss.update(&ih);
+            // anything just pressed has been seen by every component now
+            ih.advance_frame();
+            // something's animating, so don't wait for input to update again
+            let animating = commands.iter().any(|command| matches!(command, UpdateCommand::RequestRedraw));
+            if !execute_commands(commands, &mut ws, &mut ss, &mut document_path, fixed_title){
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            *control_flow = if animating { ControlFlow::Poll } else { ControlFlow::Wait };
+            if ss.is_dirty(){
+                ws.window().request_redraw();
+            }
+        },
+        // The system can ask for a redraw too, ie when the window is uncovered,
+        // so this always draws even if the scene isn't dirty
         Event::RedrawRequested(id) if id == ws.window().id() => {
-            ss.update(&ih);
-            match ws.render(ss.get_components()){
+            ss.pre_render(&mut ws);
+            match ws.render(&ss.get_components()){
                 Ok(_) => {},
-                Err(wgpu::SurfaceError::Lost) => ws.resize(*ws.size()),
+                Err(wgpu::SurfaceError::Lost) => {
+                    ws.resize(*ws.size());
+                    ss.mark_dirty();
+                },
                 // The system is out of memory, we should probably quit
                 Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                 // All other errors (Outdated, Timeout) should be resolved by the next frame
-                Err(e) => eprintln!("{:?}", e),
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    ss.mark_dirty();
+                },
             }
         },
         _ => {}
     });
 }
 
+// Builds the scene on backend, renders it once and saves it
+fn render_png<B>(document: &file_reader::Document, backend: &mut B, background: Option<render::color::Color>, png: &str)
+where
+    B: RenderBackend<Output = image::RgbaImage>,
+    B::Error: std::fmt::Display
+{
+    if let Some(background) = background{
+        backend.set_clear_color(background);
+    }
+    let mut ss = SceneState::from_document(document, backend);
+    ss.update(&input_handler::InputHandler::new_default());
+    ss.pre_render(backend);
+    let saved = backend.render(&ss.get_components())
+        .map_err(|e| e.to_string())
+        .and_then(|image| image.save(png).map_err(|e| e.to_string()));
+    if let Err(e) = saved{
+        eprintln!("error: couldn't save {}: {}", png, e);
+        std::process::exit(1);
+    }
+}
+
+// Renders the document once without a window and saves it
+async fn screenshot(arguments: cli::Arguments, png: String) {
+    env_logger::init();
+    let document = match file_reader::read_path(&arguments.file){
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let width = arguments.width.unwrap_or(800);
+    let height = arguments.height.unwrap_or(600);
+    if !arguments.software{
+        match render::headless::HeadlessState::new(width, height).await{
+            Ok(mut hs) => return render_png(&document, &mut hs, arguments.background, &png),
+            Err(e) => log::warn!("{}, falling back to software rendering", e),
+        }
+    }
+    let mut sr = render::software::SoftwareRenderer::new(width, height);
+    render_png(&document, &mut sr, arguments.background, &png);
+}
+
 fn main(){
-    pollster::block_on(run());
+    match cli::parse_arguments(std::env::args().skip(1)){
+        Ok(cli::Command::Open(mut arguments)) => match arguments.png.take(){
+            Some(png) => pollster::block_on(screenshot(arguments, png)),
+            None => pollster::block_on(run(arguments)),
+        },
+        Ok(cli::Command::Help) => println!("{}", cli::USAGE),
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, cli::USAGE);
+            std::process::exit(2);
+        }
+    }
 }
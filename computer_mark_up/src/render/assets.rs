@@ -0,0 +1,155 @@
+use std::{cell::Cell, path::{Path, PathBuf}, rc::{Rc, Weak}};
+
+use hashbrown::HashMap;
+
+/*
+ * Keeps track of every texture a backend has loaded
+ * Image files are only loaded once, asking for the
+ * same one again gives back the texture that's already there
+ *
+ * Components hold on to TextureHandles, and once the last handle
+ * to a texture is dropped it's freed the next time the backend's
+ * free_unused_textures is called
+ */
+
+#[derive(Debug)]
+struct TextureSlot{
+    index: u32,
+    // in pixels, update_image can change it
+    size: Cell<[u32; 2]>
+}
+
+// A texture loaded into a backend
+// Cloning is cheap, and the texture stays loaded while any clone is around
+#[derive(Clone, Debug)]
+pub struct TextureHandle(Rc<TextureSlot>);
+
+impl TextureHandle{
+    // What goes in ComponentVertex.texture_index
+    pub fn index(&self) -> u32{
+        self.0.index
+    }
+
+    pub fn size(&self) -> [u32; 2]{
+        self.0.size.get()
+    }
+
+    pub(crate) fn set_size(&self, size: [u32; 2]){
+        self.0.size.set(size);
+    }
+}
+
+// Two handles are equal when they're for the same texture
+impl PartialEq for TextureHandle{
+    fn eq(&self, other: &Self) -> bool{
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TextureHandle{}
+
+#[derive(Debug, Default)]
+pub struct AssetManager{
+    files: HashMap<PathBuf, Weak<TextureSlot>>,
+    // Every texture handed out, shared or not
+    // The index is kept alongside since it can't be read through a dead Weak
+    loaded: Vec<(u32, Weak<TextureSlot>)>
+}
+
+impl AssetManager{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    // The texture for an image file, if one is still loaded
+    pub fn file(&self, path: &Path) -> Option<TextureHandle>{
+        self.files.get(path)?.upgrade().map(TextureHandle)
+    }
+
+    pub fn add_file(&mut self, path: PathBuf, index: u32, size: [u32; 2]) -> TextureHandle{
+        let handle = self.add(index, size);
+        self.files.insert(path, Rc::downgrade(&handle.0));
+        handle
+    }
+
+    // For textures nothing else will ask for, ie glyph atlas pages
+    pub fn add(&mut self, index: u32, size: [u32; 2]) -> TextureHandle{
+        let slot = Rc::new(TextureSlot{ index, size: Cell::new(size) });
+        self.loaded.push((index, Rc::downgrade(&slot)));
+        TextureHandle(slot)
+    }
+
+    // The indices of textures that nothing has a handle to anymore
+    // They're forgotten about here, so the backend has to free them
+    pub fn take_unused(&mut self) -> Vec<u32>{
+        let mut unused = Vec::new();
+        self.loaded.retain(|(index, slot)| {
+            let used = slot.strong_count() > 0;
+            if !used{
+                unused.push(*index);
+            }
+            used
+        });
+        self.files.retain(|_, slot| slot.strong_count() > 0);
+        unused
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use std::path::Path;
+
+    use super::*;
+    use crate::render::{backend::RenderBackend, software::SoftwareRenderer};
+
+    fn test_image(name: &str) -> PathBuf{
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../TestFiles/images").join(name)
+    }
+
+    #[test]
+    fn the_same_file_is_only_loaded_once(){
+        let mut backend = SoftwareRenderer::new(10, 10);
+        let first = backend.load_image_file(&test_image("quadrants.png")).unwrap();
+        // a different way of getting to the same file
+        let again = backend.load_image_file(&test_image("../images/quadrants.png")).unwrap();
+        assert_eq!(first.index(), again.index());
+        assert_eq!(first, again);
+
+        let other = backend.load_image_file(&test_image("gradient.jpg")).unwrap();
+        assert_ne!(first.index(), other.index());
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn unused_once_the_last_handle_is_dropped(){
+        let mut assets = AssetManager::new();
+        let handle = assets.add_file(PathBuf::from("a.png"), 3, [1, 1]);
+        let kept = assets.add(5, [1, 1]);
+        let clone = handle.clone();
+
+        drop(handle);
+        // the clone still holds on to it
+        assert!(assets.take_unused().is_empty());
+        assert_eq!(assets.file(Path::new("a.png")), Some(clone.clone()));
+
+        drop(clone);
+        assert_eq!(assets.take_unused(), vec![3]);
+        assert_eq!(assets.file(Path::new("a.png")), None);
+        // it's only handed back once
+        assert!(assets.take_unused().is_empty());
+        assert_eq!(kept.index(), 5);
+    }
+
+    #[test]
+    fn freed_textures_make_room_for_new_ones(){
+        let mut backend = SoftwareRenderer::new(10, 10);
+        let path = test_image("quadrants.png");
+        let index = backend.load_image_file(&path).unwrap().index();
+        // nothing kept the handle
+        backend.free_unused_textures();
+        let reloaded = backend.load_image_file(&path).unwrap();
+        assert_eq!(reloaded.index(), index);
+        let image = image::open(&path).unwrap();
+        assert_eq!(reloaded.size(), [image.width(), image.height()]);
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use crate::components::ComponentObject;
+
+use super::{assets::{AssetManager, TextureHandle}, color::Color};
+
+// What the screen is cleared to when nothing else is asked for
+pub const DEFAULT_CLEAR_COLOR: [f32; 4] = [0.1, 0.2, 0.3, 1.0];
+
+/*
+ * Anything components can be drawn with
+ * WindowState and HeadlessState draw on the gpu,
+ * SoftwareRenderer draws on the cpu
+ * Solid colors go straight into ComponentVertex.color,
+ * textures are only for images and glyphs
+ * Components hold TextureHandles from the load_ methods and put
+ * TextureHandle.index() in ComponentVertex.texture_index
+ */
+pub trait RenderBackend{
+    // What a finished render gives back, ie an image
+    type Output;
+    type Error;
+
+    // What's loaded, so nothing is loaded twice
+    fn assets(&mut self) -> &mut AssetManager;
+
+    // Makes a texture out of an image and returns its index
+    // Indices of freed textures get handed out again
+    fn create_texture(&mut self, image: &image::RgbaImage) -> u32;
+
+    // Replaces the pixels of a texture, the size can change
+    fn write_texture(&mut self, index: u32, image: &image::RgbaImage);
+
+    fn free_texture(&mut self, index: u32);
+
+    fn set_clear_color(&mut self, color: Color);
+
+    // The size in pixels of what is being drawn onto
+    fn target_size(&self) -> [u32; 2];
+
+    // Components come in draw order along with the layer each is on,
+    // see SceneState::get_components
+    fn render(&mut self, components: &[(i32, &dyn ComponentObject)]) -> Result<Self::Output, Self::Error>;
+
+    // A texture of a whole image that isn't shared, ie one that gets updated
+    fn load_image(&mut self, image: &image::RgbaImage) -> TextureHandle{
+        let index = self.create_texture(image);
+        self.assets().add(index, image.dimensions().into())
+    }
+
+    // Decodes a png, jpeg or anything else the image crate understands
+    // Loading the same file again gives back the same texture
+    fn load_image_file(&mut self, path: &Path) -> Result<TextureHandle, image::ImageError>{
+        // the same file can be reached by more than one path
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if let Some(handle) = self.assets().file(&key){
+            return Ok(handle);
+        }
+        let image = image::open(path)?.to_rgba8();
+        let index = self.create_texture(&image);
+        Ok(self.assets().add_file(key, index, image.dimensions().into()))
+    }
+
+    // Replaces the pixels of a texture from load_image
+    fn update_image(&mut self, texture: &TextureHandle, image: &image::RgbaImage){
+        self.write_texture(texture.index(), image);
+        texture.set_size(image.dimensions().into());
+    }
+
+    // Frees every texture that no component has a handle to anymore
+    fn free_unused_textures(&mut self){
+        for index in self.assets().take_unused(){
+            self.free_texture(index);
+        }
+    }
+}
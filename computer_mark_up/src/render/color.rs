@@ -1,124 +1,108 @@
-use std::mem::transmute;
-
-use super::texture::Texture;
-
 // rgba
 // Where each value is between 0 and 1
 // where 0 is 0 and 1 is 255
 #[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Color([f32; 4]);
 
 impl Color{
+    pub const WHITE: Self = Self([1.0, 1.0, 1.0, 1.0]);
+
     pub fn new(rgba: [f32; 4]) -> Self{
         Self(rgba)
     }
 
-    pub fn as_bytes(&self) -> Box<[u8]>{
-        // This method turns Color into just being an array of unsized 8 bit
-        // integers. Since these are basically just bytes, it's treated as a byte
-        // The length is 16 since that's the size of the Color struct
-        // Unsafe is used since transmute is an unsafe function
-        unsafe{
-            Box::new(
-                transmute::<Color, [u8; 16]>(*self)
-            )
-        }
+    pub fn rgba(&self) -> [f32; 4]{
+        self.0
     }
 
-    pub fn match_buffer(&self) -> Box<[u8]>{
-        // for some reason the data doesn't align with the texture buffer,
-        // so padding needs to be added at the start
-        let bytes = self.as_bytes();
-        let padding = Box::new([0u8, 0u8]);
-        let combined = Vec::from([bytes, padding]).concat();
-        Box::from( combined.as_slice() )
+    // The same color, that much more see through
+    pub fn faded(&self, opacity: f32) -> Self{
+        let [r, g, b, a] = self.0;
+        Self([r, g, b, a * opacity])
     }
 
-    pub fn as_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Texture{
+    pub fn as_wgpu(&self) -> wgpu::Color{
+        let [r, g, b, a] = self.0;
+        wgpu::Color{ r: r as f64, g: g as f64, b: b as f64, a: a as f64 }
+    }
+
+    pub fn as_image(&self) -> image::RgbaImage{
         /*
          * Create a one pixel image with the color being equal to that of self
          */
-        let rgba = self.0;
         let mut pixel_image = image::RgbaImage::new(1, 1);
         pixel_image.put_pixel(
             0, 
             0, 
-            image::Rgba(rgba.map(|x| ( x * 255.0 ) as u8))
+            image::Rgba(self.as_rgba_bytes())
         );
+        pixel_image
+    }
 
-        let texture_size = wgpu::Extent3d {
-            width: 1,
-            height: 1,
-            depth_or_array_layers: 1,
-        };
-        let texture_surface = device.create_texture(
-            &wgpu::TextureDescriptor {
-                // All textures are stored as 3D, we represent our 2D texture
-                // by setting depth to 1.
-                size: texture_size,
-                mip_level_count: 1, // We'll talk about this a little later
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                // Most images are stored using sRGB so we need to reflect that here.
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                // TEXTURE_BINDING tells wgpu that we want to use this texture in shaders
-                // COPY_DST means that we want to copy data to this texture
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                // format!() returns a String, so [..] is used to get a str, which is then borrowed
-                // as label requires a &str
-                label: Some(&format!("color: {:?}", self)[..]),
-                // This is the same as with the SurfaceConfig. It
-                // specifies what texture formats can be used to
-                // create TextureViews for this texture. The base
-                // texture format (Rgba8UnormSrgb in this case) is
-                // always supported. Note that using a different
-                // texture format is not supported on the WebGL2
-                // backend.
-                view_formats: &[],
-            }
-        );
+    // The bytes as_image stores the color as
+    pub fn as_rgba_bytes(&self) -> [u8; 4]{
+        self.0.map(|x| ( x * 255.0 ) as u8)
+    }
 
-        // This tells the gpu to draw onto the texture surface
-        // If this isn't called then the texture will just be blank
-        // or random noise
-        queue.write_texture(
-            // Tells wgpu where to copy the pixel data
-            wgpu::ImageCopyTexture {
-                texture: &texture_surface,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            // The actual pixel data
-            // dereferenced to drop the Box<> then borrowed since 
-            // [u8] is unsized
-            &pixel_image,
-            // The layout of the texture
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4),
-                rows_per_image: std::num::NonZeroU32::new(1)
-            },
-            texture_size,
-        );
-        
-        let view = texture_surface.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+    // The bytes a linear color ends up as once it's written to an sRGB texture,
+    // which is what happens to the clear color
+    pub fn as_srgb_bytes(&self) -> [u8; 4]{
+        let [r, g, b, a] = self.0;
+        [encode_srgb(r), encode_srgb(g), encode_srgb(b), (a.clamp(0.0, 1.0) * 255.0).round() as u8]
+    }
+}
+
+fn decode_srgb(c: u8) -> f32{
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
 
-        Texture { 
-            label: format!("Color: {:?}", self), 
-            texture_surface, 
-            sampler, 
-            view, 
+fn encode_srgb(c: f32) -> u8{
+    let c = c.clamp(0.0, 1.0);
+    let srgb = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (srgb * 255.0).round() as u8
+}
+
+// An image with every texel's color multiplied by its alpha, which is how
+// textures are stored so that nothing see through can bleed its color into
+// what's drawn, ie the empty space around glyphs in the atlas
+// The multiply happens in linear space, and the result is stored as sRGB
+// again since that's what an Rgba8UnormSrgb texture decodes when it's sampled
+// sRGB keeps the most detail near black, where the dim premultiplied colors are
+pub fn premultiplied(image: &image::RgbaImage) -> image::RgbaImage{
+    let linear: [f32; 256] = std::array::from_fn(|c| decode_srgb(c as u8));
+    let mut premultiplied = image.clone();
+    for pixel in premultiplied.pixels_mut(){
+        let [r, g, b, a] = pixel.0;
+        // nothing to do for solid texels, which is most of any picture
+        if a == 255{
+            continue;
         }
-     }
-}
\ No newline at end of file
+        let alpha = a as f32 / 255.0;
+        let [r, g, b] = [r, g, b].map(|c| encode_srgb(linear[c as usize] * alpha));
+        pixel.0 = [r, g, b, a];
+    }
+    premultiplied
+}
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn premultiplying_happens_in_linear_space(){
+        let image = image::RgbaImage::from_fn(4, 1, |x, _| image::Rgba(match x{
+            0 => [200, 100, 50, 255],
+            // what's around glyphs in the atlas
+            1 => [0, 0, 0, 0],
+            2 => [255, 255, 255, 0],
+            _ => [255, 255, 255, 128],
+        }));
+        let premultiplied = premultiplied(&image);
+        assert_eq!(premultiplied.get_pixel(0, 0).0, [200, 100, 50, 255]);
+        assert_eq!(premultiplied.get_pixel(1, 0).0, [0, 0, 0, 0]);
+        assert_eq!(premultiplied.get_pixel(2, 0).0, [0, 0, 0, 0]);
+        // half of white's light is 188 in sRGB, not 128
+        assert_eq!(premultiplied.get_pixel(3, 0).0, [188, 188, 188, 128]);
+    }
+}
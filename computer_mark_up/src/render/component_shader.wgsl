@@ -11,18 +11,31 @@ struct ScreenInfo{
     scroll_offsets: vec2<f32>
 };
 
+// The texture index isn't passed in, the renderer binds
+// the right texture (or a white one for plain colors) for each quad
 struct ComponentInput{
     @location(0) position: vec2<f32>,
-    @location(1) texture_index: u32,
+    @location(1) color: vec4<f32>,
     @location(2) texture_position: vec2<f32>
-    //@location(1) color_or_texture: f32, // 0 false, 1 true
-    //@location(2) color_or_texture_position: vec3<f32> // should be [x, y, 0.0] if texture pos
+}
+
+// One of these per quad, see QuadInstance
+// Rects are [top left, bottom right]
+struct InstanceInput{
+    @location(0) rect: vec4<f32>,
+    @location(1) texture_rect: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) corner_radius: f32,
+    @location(4) z: f32
 }
 
 struct FragmentInput{
     @builtin(position) clip_position: vec4<f32>,
     @location(1) texture_position: vec2<f32>,
-    @location(2) texture: u32
+    @location(2) @interpolate(flat) color: vec4<f32>,
+    // only used for rounding corners
+    @location(3) @interpolate(flat) rect: vec4<f32>,
+    @location(4) @interpolate(flat) corner_radius: f32
 }
 
 // Vertex Shader
@@ -32,26 +45,73 @@ var<uniform> SCREENDETAILS: ScreenInfo;
 
 // Texture data
 @group(1) @binding(0)
-var texture_data: binding_array<texture_2d<f32>>;
+var texture_data: texture_2d<f32>;
 @group(1)@binding(1)
-var texture_sampler: binding_array<sampler>;
+var texture_sampler: sampler;
+
+// Colors are given the way they're written, in sRGB,
+// but textures are sampled and blended in linear space
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let low = c / 12.92;
+    let high = pow((c + 0.055) / 1.055, vec3(2.4));
+    return select(high, low, c <= vec3(0.04045));
+}
+
+fn to_clip_space(position: vec2<f32>) -> vec2<f32> {
+    // adjust position based on Scroll and format to screen
+    let adjusted_x = position[0] / f32(SCREENDETAILS.width) * 2.0 - 1.0;
+    // clip space has y going up, but positions have y going down
+    let adjusted_y = 1.0 - position[1] / f32(SCREENDETAILS.height) * 2.0;
+    return vec2(adjusted_x, adjusted_y);
+}
 
 @vertex
 fn vs_main(input: ComponentInput) -> FragmentInput {
-    // adjust position based on Scroll and format to screen
     var out: FragmentInput;
-    let adjusted_x = input.position[0] / f32(SCREENDETAILS.width) * 2.0 - 1.0;
-    let adjusted_y = input.position[1] / f32(SCREENDETAILS.height) * 2.0;
-    out.clip_position = vec4(adjusted_x, adjusted_y, 0.0, 1.0);
+    out.clip_position = vec4(to_clip_space(input.position), 0.0, 1.0);
     // doing colors
     out.texture_position = input.texture_position;
-    out.texture = input.texture_index;
+    out.color = vec4(srgb_to_linear(input.color.rgb), input.color.a);
+    out.corner_radius = 0.0;
+    return out;
+}
+
+// The index buffer hands out corners 0 to 3 in the
+// same order as a ComponentVertex quad, so 0 is the top left
+// and 3 is the bottom right
+@vertex
+fn vs_instance(@builtin(vertex_index) corner: u32, input: InstanceInput) -> FragmentInput {
+    var out: FragmentInput;
+    let along = vec2(f32(corner & 1u), f32(corner >> 1u));
+    let position = mix(input.rect.xy, input.rect.zw, along);
+    out.clip_position = vec4(to_clip_space(position), input.z, 1.0);
+    out.texture_position = mix(input.texture_rect.xy, input.texture_rect.zw, along);
+    out.color = vec4(srgb_to_linear(input.color.rgb), input.color.a);
+    out.rect = input.rect;
+    out.corner_radius = input.corner_radius;
     return out;
 }
 
 // Fragment Shader
 
+// How much of the pixel centered on point is inside the rounded corners,
+// same as QuadInstance::coverage
+fn coverage(point: vec2<f32>, rect: vec4<f32>, radius: f32) -> f32 {
+    let half = (rect.zw - rect.xy) / 2.0;
+    let r = min(radius, min(half.x, half.y));
+    let q = abs(point - (rect.xy + half)) - (half - r);
+    let distance = length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - r;
+    return select(1.0, clamp(0.5 - distance, 0.0, 1.0), radius > 0.0);
+}
+
+// Textures are already premultiplied when they're uploaded, see Texture::write_image,
+// so only the color (which comes in with straight alpha) and the coverage
+// are multiplied in here. Everything leaves premultiplied
+// so the blend only has to add it over what's already drawn
 @fragment
 fn fs_main(frag: FragmentInput) -> @location(0) vec4<f32>{
-    return textureSample(texture_data[frag.texture], texture_sampler[frag.texture], frag.texture_position);
+    let texel = textureSample(texture_data, texture_sampler, frag.texture_position);
+    // clip_position is the pixel's center here
+    let alpha = frag.color.a * coverage(frag.clip_position.xy, frag.rect, frag.corner_radius);
+    return vec4(texel.rgb * frag.color.rgb * alpha, texel.a * alpha);
 }
\ No newline at end of file
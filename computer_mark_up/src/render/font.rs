@@ -0,0 +1,85 @@
+use std::{fmt, sync::{atomic::{AtomicU64, Ordering}, Arc, OnceLock}};
+
+use ab_glyph::{Font as _, FontArc, PxScale};
+
+/*
+ * A TTF or OTF font that text can be drawn with
+ * Cloning is cheap, the font is only parsed once and shared
+ */
+
+// Bundled so there's always something to draw text with
+const DEFAULT_FONT: &[u8] = include_bytes!("../../../assets/fonts/DejaVuSans.ttf");
+
+// Every font loaded gets its own id, so glyphs from
+// different fonts never get mixed up in the glyph atlas
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(0);
+
+// Loaded the first time anything asks for it
+static DEFAULT: OnceLock<Font> = OnceLock::new();
+
+#[derive(Clone)]
+pub struct Font{
+    inner: FontArc,
+    // What rustybuzz shapes with
+    face: Arc<rustybuzz::Face<'static>>,
+    // shared by clones, since they're the same font
+    id: u64
+}
+
+impl fmt::Debug for Font{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        // neither of the parsed fonts can be printed
+        f.debug_struct("Font").field("id", &self.id).finish()
+    }
+}
+
+impl Font{
+    // DejaVu Sans, from assets/fonts
+    // Every call gives back the same font
+    pub fn default_font() -> Self{
+        DEFAULT.get_or_init(|| Self{
+            inner: FontArc::try_from_slice(DEFAULT_FONT).expect("the bundled font is valid"),
+            face: Arc::new(rustybuzz::Face::from_slice(DEFAULT_FONT, 0).expect("the bundled font is valid")),
+            id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed)
+        }).clone()
+    }
+
+    pub fn id(&self) -> u64{
+        self.id
+    }
+
+    pub(crate) fn inner(&self) -> &FontArc{
+        &self.inner
+    }
+
+    // For shaping
+    pub(crate) fn face(&self) -> &rustybuzz::Face<'static>{
+        &self.face
+    }
+
+    // How many pixels one of the font's design units is at a font size,
+    // shaping gives positions in design units
+    pub(crate) fn px_per_unit(&self, size: f32) -> f32{
+        size / self.inner.units_per_em().unwrap_or(1000.0)
+    }
+
+    // Font sizes are the size of an em, like in css,
+    // ab_glyph scales by the height from descender to ascender instead
+    pub(crate) fn px_scale(&self, size: f32) -> PxScale{
+        let units_per_em = self.inner.units_per_em().unwrap_or(1000.0);
+        PxScale::from(size * self.inner.height_unscaled() / units_per_em)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn the_default_font_is_only_loaded_once(){
+        let first = Font::default_font();
+        let second = Font::default_font();
+        assert_eq!(first.id(), second.id());
+        assert!(Arc::ptr_eq(&first.face, &second.face));
+    }
+}
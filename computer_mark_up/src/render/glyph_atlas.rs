@@ -0,0 +1,345 @@
+use ab_glyph::{Font as _, GlyphId};
+use hashbrown::{HashMap, HashSet};
+
+use super::{assets::TextureHandle, backend::RenderBackend, font::Font};
+
+/*
+ * A cache of rasterized glyphs shared by all the text in a scene
+ * Glyphs are packed into a few fixed size pages, each one a texture,
+ * so no matter how much text there is it only takes a handful of textures
+ *
+ * Each page is split into shelves, rows as tall as the first glyph
+ * put in them, and glyphs fill a shelf left to right
+ * When everything is full, the shelf that was used least recently
+ * is emptied and reused. Anything used during the current frame is
+ * never evicted, so text on screen always points at the right pixels
+ *
+ * Glyphs are stored in white with their coverage as the alpha, text colors
+ * them in when it's drawn, so every color shares the same copy
+ */
+
+const PAGE_SIZE: u32 = 1024;
+const MAX_PAGES: usize = 4;
+// Gap around every glyph so linear filtering never bleeds one into the next
+const PADDING: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphKey{
+    font: u64,
+    glyph: GlyphId,
+    // the font size in 64ths of a pixel, so it can be hashed
+    size: u32
+}
+
+impl GlyphKey{
+    pub fn new(font: &Font, glyph: GlyphId, size: f32) -> Self{
+        Self{
+            font: font.id(),
+            glyph,
+            size: (size * 64.0).round() as u32
+        }
+    }
+
+    pub fn size(&self) -> f32{
+        self.size as f32 / 64.0
+    }
+}
+
+// Where a glyph is in the atlas and how to place it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasGlyph{
+    // From the pen position on the baseline to the glyph's top left corner
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    // Which page the glyph is on, see GlyphAtlas.page_texture
+    pub page: usize,
+    // Top left and bottom right of the glyph in the page, from 0 to 1
+    pub texture_coords: [[f32; 2]; 2]
+}
+
+struct Shelf{
+    y: u32,
+    height: u32,
+    // where the next glyph goes
+    x: u32,
+    // the frame any glyph on here was last asked for
+    last_used: u64,
+    glyphs: Vec<GlyphKey>
+}
+
+struct Page{
+    image: image::RgbaImage,
+    shelves: Vec<Shelf>,
+    // None until it's been uploaded
+    texture: Option<TextureHandle>,
+    // changed since it was last uploaded
+    dirty: bool
+}
+
+impl Page{
+    fn new() -> Self{
+        Self{
+            image: image::RgbaImage::new(PAGE_SIZE, PAGE_SIZE),
+            shelves: Vec::new(),
+            texture: None,
+            dirty: true
+        }
+    }
+
+    // Where a new shelf would start
+    fn next_shelf_y(&self) -> u32{
+        self.shelves.last().map_or(PADDING, |shelf| shelf.y + shelf.height + PADDING)
+    }
+}
+
+struct Entry{
+    glyph: AtlasGlyph,
+    shelf: usize
+}
+
+#[derive(Default)]
+pub struct GlyphAtlas{
+    pages: Vec<Page>,
+    entries: HashMap<GlyphKey, Entry>,
+    // Glyphs with nothing to draw, like spaces
+    // remembered so they aren't outlined again every frame
+    empty: HashSet<GlyphKey>,
+    frame: u64
+}
+
+impl GlyphAtlas{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    // Called before anything asks for glyphs for the next frame
+    pub fn begin_frame(&mut self){
+        self.frame += 1;
+    }
+
+    // Rasterizes the glyph if it isn't already in the atlas
+    // None for glyphs with nothing to draw, or that can't fit anywhere
+    pub fn get_or_insert(&mut self, font: &Font, key: GlyphKey) -> Option<AtlasGlyph>{
+        if let Some(entry) = self.entries.get(&key){
+            self.pages[entry.glyph.page].shelves[entry.shelf].last_used = self.frame;
+            return Some(entry.glyph);
+        }
+        if self.empty.contains(&key){
+            return None;
+        }
+
+        let Some(outline) = font.inner().outline_glyph(key.glyph.with_scale(font.px_scale(key.size()))) else {
+            self.empty.insert(key);
+            return None;
+        };
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let Some((page_index, shelf_index, x, y)) = self.reserve(key, width, height) else {
+            log::warn!("no room in the glyph atlas for a {}x{} glyph", width, height);
+            return None;
+        };
+
+        let page = &mut self.pages[page_index];
+        // coverage is how much of each pixel the glyph covers, from 0 to 1
+        outline.draw(|gx, gy, coverage| {
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+            page.image.put_pixel(x + gx, y + gy, image::Rgba([255, 255, 255, alpha]));
+        });
+        page.dirty = true;
+
+        let page_size = PAGE_SIZE as f32;
+        let glyph = AtlasGlyph{
+            offset: [bounds.min.x, bounds.min.y],
+            size: [bounds.width(), bounds.height()],
+            page: page_index,
+            texture_coords: [
+                [x as f32 / page_size, y as f32 / page_size],
+                [(x + width) as f32 / page_size, (y + height) as f32 / page_size],
+            ]
+        };
+        self.entries.insert(key, Entry{ glyph, shelf: shelf_index });
+        Some(glyph)
+    }
+
+    // Claims room for a glyph on a shelf, giving back the page and shelf
+    // it's on and where its top left corner goes in the page
+    fn reserve(&mut self, key: GlyphKey, width: u32, height: u32) -> Option<(usize, usize, u32, u32)>{
+        let (page_index, shelf_index) = self.allocate(width, height)?;
+        let shelf = &mut self.pages[page_index].shelves[shelf_index];
+        let (x, y) = (shelf.x, shelf.y);
+        shelf.x += width + PADDING;
+        shelf.last_used = self.frame;
+        shelf.glyphs.push(key);
+        Some((page_index, shelf_index, x, y))
+    }
+
+    // Finds a shelf with room for the glyph, in order of preference:
+    // the shortest shelf it fits on, a new shelf, a new page,
+    // and finally the least recently used shelf it fits on
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(usize, usize)>{
+        if width + 2 * PADDING > PAGE_SIZE || height + 2 * PADDING > PAGE_SIZE{
+            return None;
+        }
+
+        let mut best: Option<(usize, usize, u32)> = None;
+        for (p, page) in self.pages.iter().enumerate(){
+            for (s, shelf) in page.shelves.iter().enumerate(){
+                let has_room = shelf.height >= height && shelf.x + width + PADDING <= PAGE_SIZE;
+                if has_room && best.is_none_or(|(_, _, best_height)| shelf.height < best_height){
+                    best = Some((p, s, shelf.height));
+                }
+            }
+        }
+        if let Some((p, s, _)) = best{
+            return Some((p, s));
+        }
+
+        let frame = self.frame;
+        let new_shelf = |y| Shelf{ y, height, x: PADDING, last_used: frame, glyphs: Vec::new() };
+        for (p, page) in self.pages.iter_mut().enumerate(){
+            let y = page.next_shelf_y();
+            if y + height + PADDING <= PAGE_SIZE{
+                page.shelves.push(new_shelf(y));
+                return Some((p, page.shelves.len() - 1));
+            }
+        }
+
+        if self.pages.len() < MAX_PAGES{
+            let mut page = Page::new();
+            page.shelves.push(new_shelf(PADDING));
+            self.pages.push(page);
+            return Some((self.pages.len() - 1, 0));
+        }
+
+        let (p, s) = self.pages.iter()
+            .enumerate()
+            .flat_map(|(p, page)| page.shelves.iter().enumerate().map(move |(s, shelf)| (p, s, shelf)))
+            .filter(|(_, _, shelf)| shelf.last_used < frame && shelf.height >= height)
+            .min_by_key(|(_, _, shelf)| shelf.last_used)
+            .map(|(p, s, _)| (p, s))?;
+        self.evict_shelf(p, s);
+        Some((p, s))
+    }
+
+    fn evict_shelf(&mut self, page_index: usize, shelf_index: usize){
+        let page = &mut self.pages[page_index];
+        let shelf = &mut page.shelves[shelf_index];
+        for key in shelf.glyphs.drain(..){
+            self.entries.remove(&key);
+        }
+        shelf.x = PADDING;
+        for y in shelf.y..shelf.y + shelf.height{
+            for x in 0..PAGE_SIZE{
+                page.image.put_pixel(x, y, image::Rgba([0, 0, 0, 0]));
+            }
+        }
+        page.dirty = true;
+    }
+
+    // Sends any new or changed pages to the backend
+    // so they're ready before anything is drawn with them
+    pub fn upload<B: RenderBackend>(&mut self, backend: &mut B){
+        for page in &mut self.pages{
+            match &page.texture{
+                None => page.texture = Some(backend.load_image(&page.image)),
+                Some(texture) if page.dirty => backend.update_image(texture, &page.image),
+                Some(_) => {},
+            }
+            page.dirty = false;
+        }
+    }
+
+    // The backend's texture index for a page
+    pub fn page_texture(&self, page: usize) -> Option<u32>{
+        self.pages.get(page)?.texture.as_ref().map(TextureHandle::index)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn key(glyph: u16) -> GlyphKey{
+        GlyphKey{ font: 0, glyph: GlyphId(glyph), size: 16 * 64 }
+    }
+
+    // The biggest glyph there's room for, it takes a whole page
+    const WHOLE_PAGE: u32 = PAGE_SIZE - 2 * PADDING;
+
+    #[test]
+    fn glyphs_fill_shelves_then_pages(){
+        let mut atlas = GlyphAtlas::new();
+        atlas.begin_frame();
+        // 10 across and 10 down fit on a page with the padding
+        for glyph in 0..100{
+            let (page, shelf, x, y) = atlas.reserve(key(glyph), 100, 100).unwrap();
+            assert_eq!(page, 0);
+            assert_eq!(shelf, glyph as usize / 10);
+            assert_eq!(x, PADDING + (glyph as u32 % 10) * (100 + PADDING));
+            assert_eq!(y, PADDING + shelf as u32 * (100 + PADDING));
+        }
+        assert_eq!(atlas.pages.len(), 1);
+
+        let (page, shelf, x, y) = atlas.reserve(key(100), 100, 100).unwrap();
+        assert_eq!((page, shelf, x, y), (1, 0, PADDING, PADDING));
+        // shorter glyphs still go on the shortest shelf with room
+        assert_eq!(atlas.reserve(key(101), 50, 50).unwrap().0, 1);
+        assert_eq!(atlas.pages[1].shelves.len(), 1);
+    }
+
+    #[test]
+    fn the_least_recently_used_shelf_is_evicted(){
+        let mut atlas = GlyphAtlas::new();
+        for glyph in 0..MAX_PAGES as u16{
+            atlas.begin_frame();
+            atlas.reserve(key(glyph), WHOLE_PAGE, WHOLE_PAGE).unwrap();
+        }
+        // as if glyph 0 was drawn again, which get_or_insert does for glyphs it already has
+        atlas.pages[0].shelves[0].last_used = atlas.frame;
+        atlas.entries.insert(key(1), Entry{
+            glyph: AtlasGlyph{ offset: [0.0; 2], size: [1.0; 2], page: 1, texture_coords: [[0.0; 2]; 2] },
+            shelf: 0
+        });
+
+        atlas.begin_frame();
+        let (page, shelf, _, _) = atlas.reserve(key(10), WHOLE_PAGE, WHOLE_PAGE).unwrap();
+        // glyph 1 was the one used longest ago
+        assert_eq!((page, shelf), (1, 0));
+        assert_eq!(atlas.pages[1].shelves[0].glyphs, vec![key(10)]);
+        assert!(!atlas.entries.contains_key(&key(1)));
+        assert!(atlas.pages[1].dirty);
+    }
+
+    #[test]
+    fn shelves_used_this_frame_are_never_evicted(){
+        let mut atlas = GlyphAtlas::new();
+        atlas.begin_frame();
+        for glyph in 0..MAX_PAGES as u16{
+            atlas.reserve(key(glyph), WHOLE_PAGE, WHOLE_PAGE).unwrap();
+        }
+        assert_eq!(atlas.reserve(key(10), 10, 10), None);
+        for (glyph, page) in atlas.pages.iter().enumerate(){
+            assert_eq!(page.shelves[0].glyphs, vec![key(glyph as u16)]);
+        }
+
+        // the next frame any of them can go
+        atlas.begin_frame();
+        assert!(atlas.reserve(key(10), 10, 10).is_some());
+    }
+
+    #[test]
+    fn every_color_shares_a_glyph(){
+        let font = Font::default_font();
+        let glyph = font.inner().glyph_id('A');
+        let mut atlas = GlyphAtlas::new();
+        atlas.begin_frame();
+        let placed = atlas.get_or_insert(&font, GlyphKey::new(&font, glyph, 16.0)).unwrap();
+        assert_eq!(atlas.get_or_insert(&font, GlyphKey::new(&font, glyph, 16.0)), Some(placed));
+        assert_eq!(atlas.entries.len(), 1);
+
+        // stored white, the coverage is all in the alpha
+        let page = &atlas.pages[placed.page].image;
+        assert!(page.pixels().all(|pixel| pixel.0[3] == 0 || pixel.0[..3] == [255, 255, 255]));
+        assert!(page.pixels().any(|pixel| pixel.0[3] == 255));
+    }
+}
@@ -0,0 +1,442 @@
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use crate::components::ComponentObject;
+
+use super::{
+    assets::AssetManager,
+    backend::DEFAULT_CLEAR_COLOR,
+    instance::QuadInstance,
+    vertex::{ComponentVertex, QUAD_VERTEX_ORDER}, 
+    screen_details::ScreenDetails,
+    texture::Texture, color::Color
+};
+
+// How many quads the vertex and index buffers start out holding
+const INITIAL_QUAD_CAPACITY: usize = 1024;
+
+// A texture along with the bind group that lets the shader sample it
+struct BoundTexture{
+    texture: Texture,
+    bind_group: wgpu::BindGroup
+}
+
+/*
+ * Everything needed to draw components that doesn't
+ * care where the pixels end up
+ * WindowState draws onto a window's surface with it and
+ * HeadlessState draws onto an offscreen texture
+ */
+
+pub struct GpuRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    // for components that only give vertices
+    default_render_pipeline: wgpu::RenderPipeline,
+    instance_pipeline: wgpu::RenderPipeline,
+    screen_details: ScreenDetails,
+    screen_details_buffer: wgpu::Buffer,
+    screen_details_bind_group: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    quad_buffers: QuadBuffers,
+    instance_buffer: InstanceBuffer,
+    // Indexed by ComponentVertex.texture_index, None once it's been freed
+    textures: Vec<Option<BoundTexture>>,
+    // A white pixel, bound for quads that are just a color
+    // since the shader always samples something
+    placeholder: BoundTexture,
+    assets: AssetManager,
+    // what the screen is cleared to before drawing components
+    clear_color: wgpu::Color
+}
+
+impl GpuRenderer {
+    // Features the component shader can't work without
+    // Each texture gets its own bind group, so nothing past the basics is needed
+    pub const REQUIRED_FEATURES: wgpu::Features = wgpu::Features::empty();
+
+    pub async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+        adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                features: Self::REQUIRED_FEATURES,
+                limits: wgpu::Limits::default(),
+                label: None,
+            },
+            None, // Trace path
+        ).await
+    }
+
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+
+        // screen details
+        let screen_details = ScreenDetails::new(width, height);
+        let screen_details_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ],
+            label: Some("screen_details_bind_group_layout"),
+        });
+        // written over whenever there's a frame to draw
+        let screen_details_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Screen Details Buffer"),
+                contents: bytemuck::cast_slice(&[screen_details]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let screen_details_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &screen_details_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_details_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("screen_details_bind_group"),
+        });
+
+        // Shader 
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        // This should match the filterable field of the
+                        // corresponding Texture entry above.
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None
+                    },
+                ],
+                label: Some("texture_bind_group_layout"),
+            });
+        let white = Texture::from_image(&Color::WHITE.as_image(), "placeholder", &device, &queue);
+        let placeholder = bind_texture(&device, &texture_bind_group_layout, white);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("component_shader.wgsl").into()),
+        });
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Component Render Pipeline Layout"),
+            bind_group_layouts: &[
+                &screen_details_bind_group_layout,
+                &texture_bind_group_layout
+            ],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = component_pipeline(&device, &render_pipeline_layout, &shader, format, "vs_main", ComponentVertex::desc());
+        let instance_pipeline = component_pipeline(&device, &render_pipeline_layout, &shader, format, "vs_instance", QuadInstance::desc());
+
+        let quad_buffers = QuadBuffers::new(&device, INITIAL_QUAD_CAPACITY);
+        let instance_buffer = InstanceBuffer::new(&device, INITIAL_QUAD_CAPACITY);
+
+        Self {
+            device,
+            queue,
+            default_render_pipeline: render_pipeline,
+            instance_pipeline,
+            screen_details,
+            screen_details_buffer,
+            screen_details_bind_group,
+            quad_buffers,
+            instance_buffer,
+            texture_bind_group_layout,
+            textures: Vec::new(),
+            placeholder,
+            assets: AssetManager::new(),
+            clear_color: Color::new(DEFAULT_CLEAR_COLOR).as_wgpu()
+        }
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    // Called whenever whatever is being drawn onto changes size
+    pub fn set_size(&mut self, width: u32, height: u32){
+        self.screen_details = ScreenDetails::new(width, height);
+    }
+
+    pub fn set_clear_color(&mut self, color: Color){
+        self.clear_color = color.as_wgpu();
+    }
+
+    pub fn assets(&mut self) -> &mut AssetManager{
+        &mut self.assets
+    }
+
+    // Returns the index of the new texture, which is what
+    // components use as their texture_index
+    pub fn create_texture(&mut self, image: &image::RgbaImage) -> u32{
+        let index = self.textures.iter().position(Option::is_none).unwrap_or(self.textures.len());
+        let label = format!("texture {}", index);
+        let texture = Texture::from_image(image, &label, &self.device, &self.queue);
+        let texture = Some(bind_texture(&self.device, &self.texture_bind_group_layout, texture));
+        match self.textures.get_mut(index){
+            Some(slot) => *slot = texture,
+            None => self.textures.push(texture),
+        }
+        index as u32
+    }
+
+    pub fn write_texture(&mut self, index: u32, image: &image::RgbaImage){
+        let Some(Some(bound)) = self.textures.get_mut(index as usize) else { return };
+        if bound.texture.dimensions() == image.dimensions(){
+            // same size, so the pixels can just be written over
+            bound.texture.write_image(&self.queue, image);
+        } else {
+            let texture = Texture::from_image(image, &bound.texture.label, &self.device, &self.queue);
+            *bound = bind_texture(&self.device, &self.texture_bind_group_layout, texture);
+        }
+    }
+
+    pub fn free_texture(&mut self, index: u32){
+        if let Some(slot) = self.textures.get_mut(index as usize){
+            *slot = None;
+        }
+    }
+
+    // The bind group to draw a quad with
+    fn bind_group(&self, texture_index: u32) -> &wgpu::BindGroup{
+        match self.textures.get(texture_index as usize){
+            Some(Some(bound)) => &bound.bind_group,
+            // plain colors, and anything pointing at a freed texture
+            _ => &self.placeholder.bind_group,
+        }
+    }
+
+    // Records drawing every component onto view
+    // The commands still need to be submitted to the queue
+    pub fn draw(&mut self, view: &wgpu::TextureView, components: &[(i32, &dyn ComponentObject)]) -> wgpu::CommandBuffer {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+        self.queue.write_buffer(&self.screen_details_buffer, 0, bytemuck::cast_slice(&[self.screen_details]));
+
+        // every quad from every component, in the order they're drawn
+        // Components that give instances are drawn from those,
+        // the rest from their vertices
+        let mut instances: Vec<QuadInstance> = Vec::new();
+        let mut quads: Vec<[ComponentVertex; 4]> = Vec::new();
+        let mut batches: Vec<Batch> = Vec::new();
+        for (layer, comp) in components{
+            let comp_instances = comp.get_instances();
+            if comp_instances.is_empty(){
+                for quad in comp.get_vertices(){
+                    add_to_batches(&mut batches, false, quad[0].texture_index, quads.len());
+                    quads.push(quad);
+                }
+            }
+            // z is ready for depth testing, for now the order here decides
+            for instance in comp_instances{
+                add_to_batches(&mut batches, true, instance.texture_index, instances.len());
+                instances.push(instance.on_layer(*layer));
+            }
+        }
+        if quads.len() > self.quad_buffers.capacity{
+            self.quad_buffers = QuadBuffers::new(&self.device, quads.len().next_power_of_two());
+        }
+        if instances.len() > self.instance_buffer.capacity{
+            self.instance_buffer = InstanceBuffer::new(&self.device, instances.len().next_power_of_two());
+        }
+        self.queue.write_buffer(&self.quad_buffers.vertices, 0, bytemuck::cast_slice(&quads));
+        self.queue.write_buffer(&self.instance_buffer.buffer, 0, bytemuck::cast_slice(&instances));
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_bind_group(0, &self.screen_details_bind_group, &[]);
+            render_pass.set_index_buffer(self.quad_buffers.indices.slice(..), wgpu::IndexFormat::Uint32);
+
+            // a batch for each run of quads using the same texture and pipeline,
+            // so a page of text or a pile of colored boxes is a single call
+            let mut instanced = None;
+            for batch in &batches{
+                if instanced != Some(batch.instanced){
+                    if batch.instanced{
+                        render_pass.set_pipeline(&self.instance_pipeline);
+                        render_pass.set_vertex_buffer(0, self.instance_buffer.buffer.slice(..));
+                    } else {
+                        render_pass.set_pipeline(&self.default_render_pipeline);
+                        render_pass.set_vertex_buffer(0, self.quad_buffers.vertices.slice(..));
+                    }
+                    instanced = Some(batch.instanced);
+                }
+                render_pass.set_bind_group(1, self.bind_group(batch.texture), &[]);
+                if batch.instanced{
+                    render_pass.draw_indexed(0..6, 0, batch.range.clone());
+                } else {
+                    render_pass.draw_indexed(batch.range.start * 6..batch.range.end * 6, 0, 0..1);
+                }
+            }
+        }
+
+        encoder.finish()
+    }
+}
+
+fn bind_texture(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, texture: Texture) -> BoundTexture{
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            }
+        ],
+        label: Some(&texture.label),
+    });
+    BoundTexture{ texture, bind_group }
+}
+
+// Both pipelines draw the same way and share the fragment shader,
+// they only differ in how the quads get to the vertex shader
+fn component_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, format: wgpu::TextureFormat, entry_point: &str, buffer: wgpu::VertexBufferLayout) -> wgpu::RenderPipeline{
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point,
+            // What vertex types can be passed in
+            buffers: &[buffer]
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                // the fragment shader gives premultiplied colors
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw, // standard
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None, // unnecessary
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+// Every quad drawn in a frame goes into the same pair of buffers
+// They're made once and written over each frame, and only
+// replaced with bigger ones when a frame has more quads than fit
+struct QuadBuffers{
+    vertices: wgpu::Buffer,
+    // never changes, since every quad's indices follow the same pattern
+    indices: wgpu::Buffer,
+    // in quads
+    capacity: usize
+}
+
+impl QuadBuffers{
+    fn new(device: &wgpu::Device, capacity: usize) -> Self{
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Quad Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<[ComponentVertex; 4]>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // QUAD_VERTEX_ORDER for each quad, pointing at that quad's 4 vertices
+        let indices: Vec<u32> = (0..capacity as u32)
+            .flat_map(|quad| QUAD_VERTEX_ORDER.map(|index| quad * 4 + index))
+            .collect();
+        let indices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Quad Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self{ vertices, indices, capacity }
+    }
+}
+
+// Same idea as QuadBuffers, but for QuadInstances
+// They use the first quad's worth of QuadBuffers.indices
+struct InstanceBuffer{
+    buffer: wgpu::Buffer,
+    // in instances
+    capacity: usize
+}
+
+impl InstanceBuffer{
+    fn new(device: &wgpu::Device, capacity: usize) -> Self{
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Quad Instance Buffer"),
+            size: (capacity * std::mem::size_of::<QuadInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self{ buffer, capacity }
+    }
+}
+
+// A run of quads drawn with a single call
+struct Batch{
+    instanced: bool,
+    texture: u32,
+    // quads for the vertex pipeline, instances for the instanced one
+    range: Range<u32>
+}
+
+// Adds the next quad to the last batch if it can go in it,
+// otherwise starts a new one
+fn add_to_batches(batches: &mut Vec<Batch>, instanced: bool, texture: u32, index: usize){
+    let index = index as u32;
+    match batches.last_mut(){
+        Some(batch) if batch.instanced == instanced && batch.texture == texture => batch.range.end = index + 1,
+        _ => batches.push(Batch{ instanced, texture, range: index..index + 1 }),
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fmt, num::NonZeroU32, sync::mpsc};
+
+use crate::components::ComponentObject;
+
+use super::{
+    assets::AssetManager,
+    backend::RenderBackend,
+    gpu_renderer::GpuRenderer,
+    color::Color
+};
+
+/*
+ * Draws components onto an offscreen texture instead of a window
+ * then copies the pixels back so they can be saved as an image
+ * Nothing here needs a window or a display, so it works in CI
+ */
+
+// Same format a window surface normally ends up with,
+// so screenshots match what's on screen
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Debug)]
+pub enum HeadlessError{
+    // No adapter, not even a software one, could be found
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    // Copying the rendered pixels back from the gpu failed
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for HeadlessError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Self::NoAdapter => write!(f, "no graphics adapter could be found"),
+            Self::RequestDevice(e) => write!(f, "couldn't create a device: {}", e),
+            Self::Readback(e) => write!(f, "couldn't read the rendered image back: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError{}
+
+pub struct HeadlessState{
+    renderer: GpuRenderer,
+    target: wgpu::Texture,
+    width: u32,
+    height: u32
+}
+
+impl HeadlessState{
+    pub async fn new(width: u32, height: u32) -> Result<Self, HeadlessError>{
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        // Prefer a real gpu, but anything that can run the shader will do
+        let adapter = instance.enumerate_adapters(wgpu::Backends::all())
+            .filter(|a| a.features().contains(GpuRenderer::REQUIRED_FEATURES))
+            .min_by_key(|a| a.get_info().device_type == wgpu::DeviceType::Cpu);
+        let adapter = match adapter{
+            Some(adapter) => adapter,
+            // wgpu's own software adapter, if the platform has one
+            None => instance.request_adapter(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                },
+            ).await
+                .filter(|a| a.features().contains(GpuRenderer::REQUIRED_FEATURES))
+                .ok_or(HeadlessError::NoAdapter)?,
+        };
+        log::info!("rendering headless with {:?}", adapter.get_info());
+
+        let (device, queue) = GpuRenderer::request_device(&adapter)
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Render Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TARGET_FORMAT,
+            // RENDER_ATTACHMENT to draw onto it, COPY_SRC to read it back
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        Ok(Self {
+            renderer: GpuRenderer::new(device, queue, TARGET_FORMAT, width, height),
+            target,
+            width,
+            height
+        })
+    }
+
+}
+
+impl RenderBackend for HeadlessState{
+    type Output = image::RgbaImage;
+    type Error = HeadlessError;
+
+    fn assets(&mut self) -> &mut AssetManager{
+        self.renderer.assets()
+    }
+
+    fn create_texture(&mut self, image: &image::RgbaImage) -> u32{
+        self.renderer.create_texture(image)
+    }
+
+    fn write_texture(&mut self, index: u32, image: &image::RgbaImage){
+        self.renderer.write_texture(index, image)
+    }
+
+    fn free_texture(&mut self, index: u32){
+        self.renderer.free_texture(index)
+    }
+
+    fn set_clear_color(&mut self, color: Color){
+        self.renderer.set_clear_color(color)
+    }
+
+    fn target_size(&self) -> [u32; 2]{
+        [self.width, self.height]
+    }
+
+    // Draws the components and waits for the pixels to come back
+    fn render(&mut self, components: &[(i32, &dyn ComponentObject)]) -> Result<image::RgbaImage, HeadlessError>{
+        let view = self.target.create_view(&wgpu::TextureViewDescriptor::default());
+        let draw_commands = self.renderer.draw(&view, components);
+        let device = self.renderer.device();
+        let queue = self.renderer.queue();
+
+        // Rows copied out of a texture have to be padded to a multiple of 256 bytes
+        let unpadded_row = 4 * self.width;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = unpadded_row.div_ceil(alignment) * alignment;
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless Readback Buffer"),
+            size: (padded_row * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_row),
+                    rows_per_image: NonZeroU32::new(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([draw_commands, encoder.finish()]);
+
+        // map_async only finishes once the device is polled
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| { let _ = sender.send(result); });
+        device.poll(wgpu::Maintain::Wait);
+        match receiver.recv(){
+            Ok(Ok(())) => {},
+            Ok(Err(e)) => return Err(HeadlessError::Readback(e)),
+            Err(_) => return Err(HeadlessError::Readback(wgpu::BufferAsyncError)),
+        }
+
+        let mut pixels = Vec::with_capacity((unpadded_row * self.height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_row as usize){
+                pixels.extend_from_slice(&row[..unpadded_row as usize]);
+            }
+        }
+        readback_buffer.unmap();
+
+        // the buffer is exactly width * height * 4 bytes, so this can't fail
+        Ok(image::RgbaImage::from_raw(self.width, self.height, pixels).unwrap())
+    }
+}
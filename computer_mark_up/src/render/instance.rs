@@ -0,0 +1,171 @@
+use super::{color::Color, vertex::{ComponentVertex, NO_TEXTURE}};
+
+/*
+ * Everything needed to draw one axis aligned rectangle
+ * The vertex shader turns each of these into the 4 corners
+ * of a quad, so only one record is uploaded per quad instead of 4 vertices
+ * Components with other shapes can still give ComponentVertex quads
+ * Nothing is depth tested yet, quads are drawn in the order they're given
+ */
+
+// this corresponds to InstanceInput in 'component_shader.wgsl'
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct QuadInstance{
+    // [top left, bottom right] on screen
+    pub rect: [[f32; 2]; 2],
+    // [top left, bottom right] of the part of the texture that shows
+    pub texture_rect: [[f32; 2]; 2],
+    // Multiplied with whatever is sampled, same as ComponentVertex.color
+    pub color: Color,
+    // See ComponentVertex.texture_index
+    pub texture_index: u32,
+    // How round the corners are in pixels, 0 for square ones
+    pub corner_radius: f32,
+    // Goes to the depth of the quad, between 0 and 1 with higher layers closer,
+    // see on_layer. What's drawn last still ends up on top
+    pub z: f32
+}
+
+impl QuadInstance{
+    // A rectangle filled with a color
+    pub fn colored(rect: [[f32; 2]; 2], color: Color) -> Self{
+        Self{
+            rect,
+            texture_rect: [[0.0, 0.0], [1.0, 1.0]],
+            color,
+            texture_index: NO_TEXTURE,
+            corner_radius: 0.0,
+            z: 0.5
+        }
+    }
+
+    // A rectangle showing part of a texture as it is
+    pub fn textured(rect: [[f32; 2]; 2], texture_index: u32, texture_rect: [[f32; 2]; 2]) -> Self{
+        Self{
+            rect,
+            texture_rect,
+            color: Color::WHITE,
+            texture_index,
+            corner_radius: 0.0,
+            z: 0.5
+        }
+    }
+
+    // Multiplies whatever the quad shows by color, ie to color in white glyphs
+    pub fn tinted(mut self, color: Color) -> Self{
+        self.color = color;
+        self
+    }
+
+    // Rounds the corners off, radius is in pixels
+    pub fn rounded(mut self, corner_radius: f32) -> Self{
+        self.corner_radius = corner_radius;
+        self
+    }
+
+    // Sets z for a layer, see SceneState::layer
+    // Layer 0 is at 0.5 and the rest squeeze in either side of it,
+    // so every i32 gets a depth, though far out ones can share one
+    pub fn on_layer(mut self, layer: i32) -> Self{
+        self.z = 0.5 - (layer as f32).atan() / std::f32::consts::PI;
+        self
+    }
+
+    // Makes the whole quad that much more see through
+    pub fn faded(mut self, opacity: f32) -> Self{
+        self.color = self.color.faded(opacity);
+        self
+    }
+
+    // The same quad the vertex shader makes,
+    // ordered [top left, top right, bottom left, bottom right]
+    pub fn vertices(&self) -> [ComponentVertex; 4]{
+        let [[left, top], [right, bottom]] = self.rect;
+        let [[u0, v0], [u1, v1]] = self.texture_rect;
+        let vertex = |position, texture_coords| ComponentVertex{
+            position,
+            color: self.color,
+            texture_index: self.texture_index,
+            texture_coords
+        };
+        [
+            vertex([left, top],     [u0, v0]),
+            vertex([right, top],    [u1, v0]),
+            vertex([left, bottom],  [u0, v1]),
+            vertex([right, bottom], [u1, v1])
+        ]
+    }
+
+    // How much of the pixel centered on point is inside the rounded corners,
+    // from 0 to 1, the fragment shader does the same thing
+    pub fn coverage(&self, point: [f32; 2]) -> f32{
+        if self.corner_radius <= 0.0{
+            return 1.0;
+        }
+        let [[left, top], [right, bottom]] = self.rect;
+        let half = [(right - left) / 2.0, (bottom - top) / 2.0];
+        let radius = self.corner_radius.min(half[0]).min(half[1]);
+        let center = [left + half[0], top + half[1]];
+        // signed distance to the edge of the rounded rectangle
+        let q = [0, 1].map(|axis| (point[axis] - center[axis]).abs() - (half[axis] - radius));
+        let outside = q[0].max(0.0).hypot(q[1].max(0.0));
+        let distance = outside + q[0].max(q[1]).min(0.0) - radius;
+        (0.5 - distance).clamp(0.0, 1.0)
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<QuadInstance>() as wgpu::BufferAddress,
+            // one of these for every quad, not every vertex
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: std::mem::offset_of!(QuadInstance, rect) as wgpu::BufferAddress,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x4
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::offset_of!(QuadInstance, texture_rect) as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::offset_of!(QuadInstance, color) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::offset_of!(QuadInstance, corner_radius) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::offset_of!(QuadInstance, z) as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32
+                },
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn z_of(layer: i32) -> f32{
+        QuadInstance::colored([[0.0, 0.0], [1.0, 1.0]], Color::WHITE).on_layer(layer).z
+    }
+
+    #[test]
+    fn higher_layers_are_closer(){
+        let layers = [i32::MIN, -1000, -2, -1, 0, 1, 2, 1000, i32::MAX];
+        for pair in layers.windows(2){
+            assert!(z_of(pair[0]) > z_of(pair[1]), "layers {} and {}", pair[0], pair[1]);
+        }
+        assert_eq!(z_of(0), 0.5);
+        // still in the range the depth buffer keeps
+        assert!(layers.iter().all(|layer| (0.0..=1.0).contains(&z_of(*layer))));
+    }
+}
@@ -1,5 +1,13 @@
 pub mod window_state;
+pub mod gpu_renderer;
+pub mod headless;
+pub mod software;
+pub mod backend;
 pub mod vertex;
+pub mod instance;
 pub mod screen_details;
 pub mod texture;
-pub mod color;
\ No newline at end of file
+pub mod color;
+pub mod font;
+pub mod glyph_atlas;
+pub mod assets;
\ No newline at end of file
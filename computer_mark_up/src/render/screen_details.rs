@@ -7,10 +7,10 @@ pub struct ScreenDetails{
 }
 
 impl ScreenDetails{
-    pub fn new(config: &wgpu::SurfaceConfiguration) -> Self{
+    pub fn new(width: u32, height: u32) -> Self{
         Self { 
-            width: config.width, 
-            height: config.height, 
+            width, 
+            height, 
             scroll: [0.0, 0.0]
         }
     }
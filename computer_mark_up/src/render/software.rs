@@ -0,0 +1,247 @@
+use std::convert::Infallible;
+
+use crate::components::ComponentObject;
+
+use super::{
+    assets::AssetManager,
+    backend::{RenderBackend, DEFAULT_CLEAR_COLOR},
+    color::{premultiplied, Color},
+    instance::QuadInstance,
+    vertex::{ComponentVertex, NO_TEXTURE, QUAD_VERTEX_ORDER}
+};
+
+/*
+ * Draws components on the cpu into an image
+ * This takes the same quads, colors and texture indices as the gpu pipeline
+ * and follows the same rules (back faces culled, nearest texel,
+ * later components blended over earlier ones) so the output matches it
+ * No drivers are needed, so it works anywhere
+ */
+
+pub struct SoftwareRenderer{
+    width: u32,
+    height: u32,
+    // Indexed by ComponentVertex.texture_index, None once it's been freed
+    // Stored premultiplied, the same as the gpu stores them
+    textures: Vec<Option<image::RgbaImage>>,
+    assets: AssetManager,
+    clear_color: Color
+}
+
+// Twice the signed area of the triangle a, b, p
+// Since y points down this is positive when a, b, p go clockwise on screen
+fn edge_function(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32{
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+// Pixels whose centers land exactly on an edge only belong to
+// the triangle if it's a top or left edge, so two triangles
+// sharing an edge never both draw the same pixel
+fn is_top_left(a: [f32; 2], b: [f32; 2]) -> bool{
+    let top = a[1] == b[1] && b[0] > a[0];
+    let left = b[1] < a[1];
+    top || left
+}
+
+fn srgb_to_linear(c: u8) -> f32{
+    decode_srgb(c as f32 / 255.0)
+}
+
+fn decode_srgb(c: f32) -> f32{
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> u8{
+    let c = c.clamp(0.0, 1.0);
+    let srgb = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (srgb * 255.0).round() as u8
+}
+
+// What the fragment shader gives back for an already premultiplied texel:
+// multiplied by the color in linear space, and by the color's alpha
+fn shade(texel: image::Rgba<u8>, color: Color, coverage: f32) -> [f32; 4]{
+    let [r, g, b, a] = color.rgba();
+    let tint = [decode_srgb(r), decode_srgb(g), decode_srgb(b)];
+    let alpha = a * coverage;
+    let mut out = [0.0, 0.0, 0.0, texel.0[3] as f32 / 255.0 * alpha];
+    for (i, channel) in out.iter_mut().take(3).enumerate(){
+        *channel = srgb_to_linear(texel.0[i]) * tint[i] * alpha;
+    }
+    out
+}
+
+// Draws a premultiplied src over dst the way wgpu's PREMULTIPLIED_ALPHA_BLENDING
+// does on an sRGB target, the colors are mixed in linear space and then stored as sRGB again
+fn blend(dst: image::Rgba<u8>, src: [f32; 4]) -> image::Rgba<u8>{
+    let alpha = src[3];
+    if alpha <= 0.0{
+        return dst;
+    }
+    let mut out = [0u8; 4];
+    for (i, channel) in out.iter_mut().take(3).enumerate(){
+        *channel = linear_to_srgb(src[i] + srgb_to_linear(dst.0[i]) * (1.0 - alpha));
+    }
+    let dst_alpha = dst.0[3] as f32 / 255.0;
+    out[3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+    image::Rgba(out)
+}
+
+impl SoftwareRenderer{
+    pub fn new(width: u32, height: u32) -> Self{
+        Self{
+            width,
+            height,
+            textures: Vec::new(),
+            assets: AssetManager::new(),
+            clear_color: Color::new(DEFAULT_CLEAR_COLOR)
+        }
+    }
+
+    fn sample(&self, texture_index: u32, coords: [f32; 2]) -> Option<image::Rgba<u8>>{
+        let texture = self.textures.get(texture_index as usize)?.as_ref()?;
+        let (width, height) = texture.dimensions();
+        let x = ((coords[0] * width as f32).floor() as i64).clamp(0, width as i64 - 1);
+        let y = ((coords[1] * height as f32).floor() as i64).clamp(0, height as i64 - 1);
+        Some(*texture.get_pixel(x as u32, y as u32))
+    }
+
+    // Instances are drawn as the same two triangles the gpu makes out of them,
+    // with their corners rounded off
+    fn draw_quad(&self, target: &mut image::RgbaImage, vertices: &[ComponentVertex; 4], instance: Option<&QuadInstance>){
+        for triangle in QUAD_VERTEX_ORDER.chunks(3){
+            self.draw_triangle(target, [
+                &vertices[triangle[0] as usize],
+                &vertices[triangle[1] as usize],
+                &vertices[triangle[2] as usize],
+            ], instance);
+        }
+    }
+
+    fn draw_triangle(&self, target: &mut image::RgbaImage, vertices: [&ComponentVertex; 3], instance: Option<&QuadInstance>){
+        let [v0, mut v1, mut v2] = vertices;
+        // Front faces wind counter clockwise, which gives a negative area here
+        // Triangles facing away are culled, just like the gpu pipeline does
+        // and anything with no area has nothing to draw anyway
+        let area = -edge_function(v0.position, v1.position, v2.position);
+        if area <= 0.0{
+            return;
+        }
+        // flip it to clockwise so every inside pixel has positive weights
+        std::mem::swap(&mut v1, &mut v2);
+
+        let xs = [v0.position[0], v1.position[0], v2.position[0]];
+        let ys = [v0.position[1], v1.position[1], v2.position[1]];
+        let min_x = xs.iter().copied().fold(f32::INFINITY, f32::min).floor().max(0.0) as u32;
+        let min_y = ys.iter().copied().fold(f32::INFINITY, f32::min).floor().max(0.0) as u32;
+        let max_x = (xs.iter().copied().fold(f32::NEG_INFINITY, f32::max).ceil() as i64).clamp(0, self.width as i64) as u32;
+        let max_y = (ys.iter().copied().fold(f32::NEG_INFINITY, f32::max).ceil() as i64).clamp(0, self.height as i64) as u32;
+
+        let edges = [
+            (v1.position, v2.position),
+            (v2.position, v0.position),
+            (v0.position, v1.position),
+        ];
+        // The texture index and color are flat, so they come from the first vertex
+        let texture_index = v0.texture_index;
+        let color = v0.color;
+
+        for y in min_y..max_y{
+            for x in min_x..max_x{
+                // sample at the center of the pixel
+                let p = [x as f32 + 0.5, y as f32 + 0.5];
+                let mut weights = [0.0f32; 3];
+                let mut inside = true;
+                for (i, (a, b)) in edges.iter().enumerate(){
+                    let w = edge_function(*a, *b, p);
+                    if w < 0.0 || (w == 0.0 && !is_top_left(*a, *b)){
+                        inside = false;
+                        break;
+                    }
+                    weights[i] = w / area;
+                }
+                if !inside{
+                    continue;
+                }
+
+                let shaded = if texture_index == NO_TEXTURE{
+                    // the gpu samples a white pixel for these
+                    Some((image::Rgba([255; 4]), color))
+                } else {
+                    let coords = [
+                        weights[0] * v0.texture_coords[0] + weights[1] * v1.texture_coords[0] + weights[2] * v2.texture_coords[0],
+                        weights[0] * v0.texture_coords[1] + weights[1] * v1.texture_coords[1] + weights[2] * v2.texture_coords[1],
+                    ];
+                    self.sample(texture_index, coords).map(|texel| (texel, color))
+                };
+                if let Some((texel, color)) = shaded{
+                    let coverage = instance.map_or(1.0, |instance| instance.coverage(p));
+                    let pixel = target.get_pixel_mut(x, y);
+                    *pixel = blend(*pixel, shade(texel, color, coverage));
+                }
+            }
+        }
+    }
+}
+
+impl RenderBackend for SoftwareRenderer{
+    type Output = image::RgbaImage;
+    type Error = Infallible;
+
+    fn assets(&mut self) -> &mut AssetManager{
+        &mut self.assets
+    }
+
+    fn create_texture(&mut self, image: &image::RgbaImage) -> u32{
+        match self.textures.iter().position(Option::is_none){
+            Some(free) => {
+                self.textures[free] = Some(premultiplied(image));
+                free as u32
+            },
+            None => {
+                self.textures.push(Some(premultiplied(image)));
+                (self.textures.len() - 1) as u32
+            },
+        }
+    }
+
+    fn write_texture(&mut self, index: u32, image: &image::RgbaImage){
+        if let Some(Some(texture)) = self.textures.get_mut(index as usize){
+            *texture = premultiplied(image);
+        }
+    }
+
+    fn free_texture(&mut self, index: u32){
+        if let Some(texture) = self.textures.get_mut(index as usize){
+            *texture = None;
+        }
+    }
+
+    fn set_clear_color(&mut self, color: Color){
+        self.clear_color = color;
+    }
+
+    fn target_size(&self) -> [u32; 2]{
+        [self.width, self.height]
+    }
+
+    // Layers don't matter here, the order is enough without a depth buffer
+    fn render(&mut self, components: &[(i32, &dyn ComponentObject)]) -> Result<image::RgbaImage, Infallible>{
+        let mut target = image::RgbaImage::from_pixel(
+            self.width,
+            self.height,
+            image::Rgba(self.clear_color.as_srgb_bytes())
+        );
+        for (_, comp) in components{
+            let instances = comp.get_instances();
+            if instances.is_empty(){
+                for vertices in comp.get_vertices(){
+                    self.draw_quad(&mut target, &vertices, None);
+                }
+            }
+            for instance in &instances{
+                self.draw_quad(&mut target, &instance.vertices(), Some(instance));
+            }
+        }
+        Ok(target)
+    }
+}
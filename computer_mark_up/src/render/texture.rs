@@ -1,13 +1,4 @@
-use image::GenericImageView;
-
-use super::color::Color;
-
-#[derive(Debug)]
-pub enum TextureType{
-    // The same color across a texture
-    Color(Color),
-    Texture(Texture)
-}
+use super::color::premultiplied;
 
 #[derive(Debug)]
 pub struct Texture{
@@ -18,11 +9,9 @@ pub struct Texture{
 }
 
 impl Texture{
-    pub fn new(img_data: &[u8], label: &str, device: &wgpu::Device, queue: &wgpu::Queue, texture_bind_group_layout: wgpu::BindGroupLayout) -> Self{
-        let diffuse_image = image::load_from_memory(img_data).unwrap();
-        let rgba_data = diffuse_image.to_rgba8();
-        
-        let dimensions = diffuse_image.dimensions();
+    // For images that are already decoded, ie glyph atlases
+    pub fn from_image(rgba_data: &image::RgbaImage, label: &str, device: &wgpu::Device, queue: &wgpu::Queue) -> Self{
+        let dimensions = rgba_data.dimensions();
 
         let texture_size = wgpu::Extent3d {
             width: dimensions.0,
@@ -45,43 +34,56 @@ impl Texture{
             }
         );
         
+        let view = texture_surface.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            // Nearest both ways, SoftwareRenderer picks texels the same way
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let texture = Self{
+            label: String::from(label),
+            texture_surface,
+            sampler,
+            view
+        };
+        texture.write_image(queue, rgba_data);
+        texture
+    }
+
+    pub fn dimensions(&self) -> (u32, u32){
+        let size = self.texture_surface.size();
+        (size.width, size.height)
+    }
+
+    // Overwrites every pixel, the image has to be the same size as the texture
+    // Images come in with straight alpha and are stored premultiplied
+    pub fn write_image(&self, queue: &wgpu::Queue, rgba_data: &image::RgbaImage){
+        let rgba_data = &premultiplied(rgba_data);
+        let dimensions = rgba_data.dimensions();
         // load rgb data onto the texture surface
         queue.write_texture(
             // Tells wgpu where to copy the pixel data
             wgpu::ImageCopyTexture {
-                texture: &texture_surface,
+                texture: &self.texture_surface,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
             // The actual pixel data
-            &rgba_data,
+            rgba_data,
             // The layout of the texture
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
                 rows_per_image: std::num::NonZeroU32::new(dimensions.1),
             },
-            texture_size,
+            self.texture_surface.size(),
         );
-        
-        let view = texture_surface.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        Self{
-            label: String::from(label),
-            texture_surface,
-            sampler,
-            view
-        }
     }
-
 }
\ No newline at end of file
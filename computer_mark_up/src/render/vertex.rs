@@ -1,11 +1,23 @@
-use std::mem::size_of;
+// The two triangles making up a quad from get_vertices(),
+// which is ordered [top left, top right, bottom left, bottom right]
+// Both triangles wind counter clockwise once on screen
+pub const QUAD_VERTEX_ORDER: [u32; 6] = [0u32, 2u32, 1u32, 1u32, 2u32, 3u32];
+
+use super::color::Color;
+
+// The texture_index of quads that are just a solid color
+pub const NO_TEXTURE: u32 = u32::MAX;
 
 // this corresponds to ComponentInput in 'component_shader.wgsl'
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ComponentVertex{
     pub position: [f32; 2],
-    // The index of the texture in WindowState.textures
+    // Multiplied with whatever is sampled from the texture,
+    // or drawn as is when there's no texture
+    pub color: Color,
+    // The texture to sample, see TextureHandle.index
+    // The shader never sees this, the renderer binds the texture for it
     pub texture_index: u32,
     pub texture_coords: [f32; 2]
 }
@@ -22,12 +34,12 @@ impl ComponentVertex{
                     format: wgpu::VertexFormat::Float32x2
                 },
                 wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    offset: std::mem::offset_of!(ComponentVertex, color) as wgpu::BufferAddress,
                     shader_location: 1,
-                    format: wgpu::VertexFormat::Uint32
+                    format: wgpu::VertexFormat::Float32x4
                 },
                 wgpu::VertexAttribute {
-                    offset: (std::mem::size_of::<[f32; 2]>() + std::mem::size_of::<u32>())as wgpu::BufferAddress,
+                    offset: std::mem::offset_of!(ComponentVertex, texture_coords) as wgpu::BufferAddress,
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x2
                 },
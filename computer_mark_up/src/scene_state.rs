@@ -1,35 +1,958 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use hashbrown::{HashMap, HashSet};
+
 use crate::{
     components::{
-        Component, 
-        ComponentObject
-    }, 
-    input_handler::InputHandler
+        Component,
+        ComponentObject,
+        HoverComponent,
+        ImageComponent,
+        ImageFit,
+        SquareComponent,
+        TextAlign,
+        TextComponent,
+        TextOverflow,
+        TextStyle,
+        DEFAULT_FONT_SIZE
+    },
+    file_reader::{Align, Definition, Document, Fit, Geometry, Node, Overflow, Tag},
+    input_handler::InputHandler,
+    render::{assets::TextureHandle, backend::RenderBackend, color::Color, font::Font, glyph_atlas::GlyphAtlas},
+    update_commands::UpdateCommand
 };
 
+// Used for any tag that doesn't give itself a color
+const DEFAULT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// Handed out for everything added to a scene
+// and never reused, even once it's been removed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(u64);
+
+/*
+ * A component and everything nested under it, outside of a scene
+ * This is what gets inserted into and taken out of a SceneState
+ * Positions are relative to the parent's top left corner
+ */
+pub struct Subtree{
+    // None for things that only group their children, like <main>
+    component: Option<Component>,
+    position: [f32; 2],
+    // multiplied with the parent's, so children fade along with it
+    // It's applied to each component separately, not to the group as a whole
+    opacity: f32,
+    // None to be on the same layer as the parent, see SceneState.layer
+    z_index: Option<i32>,
+    // The box FLOAT children fill
+    size: [f32; 2],
+    // Takes the parent's size, or the screen's for roots, see SceneState.relayout
+    floating: bool,
+    children: Vec<Subtree>
+}
+
+impl Subtree{
+    // The component's own corner is taken as relative to its parent
+    pub fn new<C: ComponentObject + 'static>(component: C) -> Self{
+        Self{
+            position: component.position(),
+            component: Some(Box::new(component)),
+            opacity: 1.0,
+            z_index: None,
+            size: [0.0, 0.0],
+            floating: false,
+            children: Vec::new()
+        }
+    }
+
+    pub fn container(position: [f32; 2]) -> Self{
+        Self{
+            component: None,
+            position,
+            opacity: 1.0,
+            z_index: None,
+            size: [0.0, 0.0],
+            floating: false,
+            children: Vec::new()
+        }
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self{
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: Option<i32>) -> Self{
+        self.z_index = z_index;
+        self
+    }
+
+    pub fn with_size(mut self, size: [f32; 2]) -> Self{
+        self.size = size;
+        self
+    }
+
+    // Resized along with whatever it's inside, like FLOAT tags
+    pub fn floating(mut self) -> Self{
+        self.floating = true;
+        self
+    }
+
+    pub fn push_child(&mut self, child: Subtree){
+        self.children.push(child);
+    }
+}
+
+// Loads the file an <image> points at, None if it doesn't point at one that works
+// Images used more than once in a scene share a texture
+fn load_image<B: RenderBackend>(document: &Document, node: &Node, backend: &mut B) -> Option<TextureHandle>{
+    let Some(src) = node.attribute_value("src") else {
+        log::warn!("<image> at line {}, column {} has no src", node.line, node.column);
+        return None;
+    };
+    let path = document.resolve(src);
+    match backend.load_image_file(&path){
+        Ok(texture) => Some(texture),
+        Err(e) => {
+            log::warn!("couldn't load the image '{}': {}", path.display(), e);
+            None
+        },
+    }
+}
+
+// Images take their own size when the definition leaves it out,
+// and keep their shape when only the width or height is given
+fn image_box(definition: &Definition, [image_width, image_height]: [f32; 2]) -> [f32; 2]{
+    match (definition.width, definition.height){
+        (Some(width), Some(height)) => [width, height],
+        (Some(width), None) => [width, width * image_height / image_width],
+        (None, Some(height)) => [height * image_width / image_height, height],
+        (None, None) => [image_width, image_height],
+    }
+}
+
+fn text_align(align: Align) -> TextAlign{
+    match align{
+        Align::Start => TextAlign::Start,
+        Align::End => TextAlign::End,
+        Align::Left => TextAlign::Left,
+        Align::Center => TextAlign::Center,
+        Align::Right => TextAlign::Right,
+        Align::Justify => TextAlign::Justify,
+    }
+}
+
+fn text_overflow(overflow: Overflow) -> TextOverflow{
+    match overflow{
+        Overflow::Visible => TextOverflow::Visible,
+        Overflow::Clip => TextOverflow::Clip,
+        Overflow::Ellipsis => TextOverflow::Ellipsis,
+    }
+}
+
+fn image_fit(fit: Fit) -> ImageFit{
+    match fit{
+        Fit::Stretch => ImageFit::Stretch,
+        Fit::Contain => ImageFit::Contain,
+        Fit::Cover => ImageFit::Cover,
+        Fit::None => ImageFit::None,
+    }
+}
+
+// Text in markup is indented along with the tags around it,
+// that indentation and any blank lines at the ends aren't part of the text
+fn markup_text(text: &str) -> String{
+    text.lines()
+        .map(str::trim)
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+impl fmt::Debug for Subtree{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        // components can't be printed, so just say if there is one
+        f.debug_struct("Subtree")
+            .field("has_component", &self.component.is_some())
+            .field("position", &self.position)
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+struct SceneNode{
+    component: Option<Component>,
+    // relative to the parent's top left corner, or the screen's for roots
+    local_position: [f32; 2],
+    // just this node's, see SceneState.opacity
+    local_opacity: f32,
+    z_index: Option<i32>,
+    size: [f32; 2],
+    floating: bool,
+    parent: Option<ComponentId>,
+    // in draw order
+    children: Vec<ComponentId>
+}
+
 pub struct SceneState{
-    components: Vec<Component>
+    nodes: HashMap<ComponentId, SceneNode>,
+    // everything without a parent, in draw order
+    roots: Vec<ComponentId>,
+    next_id: u64,
+    focused: Option<ComponentId>,
+    // How far the document is scrolled, everything is drawn this much up and left
+    scroll: [f32; 2],
+    // Shared by every text component in the scene
+    glyph_atlas: Rc<RefCell<GlyphAtlas>>,
+    // Something looks different since the last pre_render
+    dirty: bool
+}
+
+// Walks a scene parent first, so parents come before their children
+// and earlier siblings before later ones
+// This is also the draw order for everything on the same layer
+pub struct DepthFirst<'a>{
+    scene: &'a SceneState,
+    stack: Vec<ComponentId>
+}
+
+impl Iterator for DepthFirst<'_>{
+    type Item = ComponentId;
+
+    fn next(&mut self) -> Option<ComponentId>{
+        let id = self.stack.pop()?;
+        self.stack.extend(self.scene.children(id).iter().rev());
+        Some(id)
+    }
+}
+
+// Walks from a component's parent up to the root it's under
+pub struct Ancestors<'a>{
+    scene: &'a SceneState,
+    next: Option<ComponentId>
+}
+
+impl Iterator for Ancestors<'_>{
+    type Item = ComponentId;
+
+    fn next(&mut self) -> Option<ComponentId>{
+        let id = self.next?;
+        self.next = self.scene.parent(id);
+        Some(id)
+    }
 }
 
 impl SceneState{
     pub fn new_empty() -> Self {
-        Self { 
-            components: Vec::new()
+        Self {
+            nodes: HashMap::new(),
+            roots: Vec::new(),
+            next_id: 0,
+            focused: None,
+            scroll: [0.0, 0.0],
+            glyph_atlas: Rc::new(RefCell::new(GlyphAtlas::new())),
+            // nothing has been drawn yet
+            dirty: true
          }
     }
 
-    // this may have to be changed to 'a eventually
-    pub fn add_component<C: ComponentObject + 'static>(&mut self, component: C){
-        self.components.push( Box::new(component) )
+    // Builds a component for every tag in the document
+    // Any colors the tags use are loaded into the backend
+    // so the components can point at them
+    pub fn from_document<B: RenderBackend>(document: &Document, backend: &mut B) -> Self{
+        let mut scene = Self::new_empty();
+        let [width, height] = backend.target_size();
+        let screen_size = [width as f32, height as f32];
+        let font = Font::default_font();
+        for node in &document.nodes{
+            if let Some(subtree) = scene.build_subtree(document, node, screen_size, &font, backend){
+                scene.insert_subtree(None, subtree);
+            }
+        }
+        scene
+    }
+
+    // parent_size is the area FLOAT tags fill
+    fn build_subtree<B: RenderBackend>(
+        &self,
+        document: &Document,
+        node: &Node,
+        parent_size: [f32; 2],
+        font: &Font,
+        backend: &mut B
+    ) -> Option<Subtree>{
+        if node.tag == Tag::Comment{
+            return None;
+        }
+        for unknown in &node.definition.unknown_keys{
+            log::warn!(
+                "<{}> at line {}, column {} has an unknown property '{}'",
+                node.tag.name(), unknown.line, unknown.column, unknown.key
+            );
+        }
+
+        // corners are relative to the parent from here on
+        let (corner, mut size) = match node.geometry{
+            Geometry::Float => ([0.0, 0.0], parent_size),
+            Geometry::Custom => (node.definition.position(), node.definition.size()),
+        };
+        // Text only wraps or gets cut off if it's been given a size
+        let max_size = match node.geometry{
+            Geometry::Float => parent_size.map(Some),
+            Geometry::Custom => [node.definition.width, node.definition.height],
+        };
+        let color = Color::new(node.definition.color.unwrap_or(DEFAULT_COLOR));
+        let corner_radius = node.definition.corner_radius.unwrap_or(0.0);
+
+        let mut subtree = match node.tag{
+            Tag::Square => {
+                Subtree::new(SquareComponent::new(corner, size[0], size[1], color).with_corner_radius(corner_radius))
+            },
+            Tag::Hover => {
+                let hover = node.definition.hover_color.map_or(color, Color::new);
+                Subtree::new(HoverComponent::new(corner, size[0], size[1], color, hover).with_corner_radius(corner_radius))
+            },
+            Tag::Text => {
+                let style = TextStyle{
+                    size: node.definition.font_size.unwrap_or(DEFAULT_FONT_SIZE),
+                    color,
+                    line_height: node.definition.line_height,
+                    align: node.definition.align.map(text_align).unwrap_or_default(),
+                    overflow: node.definition.overflow.map(text_overflow).unwrap_or_default()
+                };
+                let text = markup_text(&node.text);
+                Subtree::new(TextComponent::new(corner, &text, font, &style, max_size, self.glyph_atlas()))
+            },
+            Tag::Image => match load_image(document, node, backend){
+                Some(texture) => {
+                    if node.geometry == Geometry::Custom{
+                        size = image_box(&node.definition, texture.size().map(|pixels| pixels as f32));
+                    }
+                    let fit = node.definition.fit.map(image_fit).unwrap_or_default();
+                    Subtree::new(ImageComponent::new(corner, size[0], size[1], fit, texture).with_corner_radius(corner_radius))
+                },
+                // nothing to draw, but the children still get built
+                None => Subtree::container(corner),
+            },
+            // containers don't draw anything themselves
+            Tag::Main | Tag::Comment => Subtree::container(corner),
+        };
+
+        for child in &node.children{
+            if let Some(child) = self.build_subtree(document, child, size, font, backend){
+                subtree.push_child(child);
+            }
+        }
+        let subtree = subtree
+            .with_opacity(node.definition.opacity.unwrap_or(1.0))
+            .with_z_index(node.definition.z_index)
+            .with_size(size);
+        Some(match node.geometry{
+            Geometry::Float => subtree.floating(),
+            Geometry::Custom => subtree,
+        })
+    }
+
+    // Resizes everything that fills what it's inside,
+    // for when the screen has changed size
+    pub fn relayout(&mut self, screen_size: [f32; 2]){
+        for root in self.roots.clone(){
+            self.resize_floating(root, screen_size);
+        }
+        self.dirty = true;
+    }
+
+    fn resize_floating(&mut self, id: ComponentId, parent_size: [f32; 2]){
+        let node = self.nodes.get_mut(&id).unwrap();
+        if node.floating{
+            node.size = parent_size;
+            if let Some(component) = &mut node.component{
+                component.set_size(parent_size);
+            }
+        }
+        let size = node.size;
+        for child in node.children.clone(){
+            self.resize_floating(child, size);
+        }
+    }
+
+    // Adds a component at the top level, its corner is relative to the screen
+    pub fn add_component<C: ComponentObject + 'static>(&mut self, component: C) -> ComponentId{
+        self.insert_subtree(None, Subtree::new(component))
+            .expect("a root always has somewhere to go")
+    }
+
+    // Adds a component as the last child of parent
+    // its corner is relative to the parent's
+    pub fn add_child<C: ComponentObject + 'static>(&mut self, parent: ComponentId, component: C) -> Option<ComponentId>{
+        self.insert_subtree(Some(parent), Subtree::new(component))
+    }
+
+    // Puts subtree after parent's other children, or after the other roots if parent is None
+    // Returns the id of the subtree's top component, or None without adding
+    // anything if parent isn't in the scene
+    pub fn insert_subtree(&mut self, parent: Option<ComponentId>, subtree: Subtree) -> Option<ComponentId>{
+        let (parent_corner, parent_opacity) = match parent{
+            Some(parent) => (self.absolute_position(parent)?, self.opacity(parent)?),
+            None => ([-self.scroll[0], -self.scroll[1]], 1.0),
+        };
+        let id = self.insert_node(parent, subtree, parent_corner, parent_opacity);
+        match parent{
+            Some(parent) => self.nodes.get_mut(&parent).unwrap().children.push(id),
+            None => self.roots.push(id),
+        }
+        self.dirty = true;
+        Some(id)
+    }
+
+    // Doesn't link the new node into its parent, insert_subtree does that
+    fn insert_node(&mut self, parent: Option<ComponentId>, subtree: Subtree, parent_corner: [f32; 2], parent_opacity: f32) -> ComponentId{
+        let id = ComponentId(self.next_id);
+        self.next_id += 1;
+
+        let corner = [parent_corner[0] + subtree.position[0], parent_corner[1] + subtree.position[1]];
+        let opacity = parent_opacity * subtree.opacity;
+        let mut component = subtree.component;
+        if let Some(component) = &mut component{
+            component.set_position(corner);
+            component.set_opacity(opacity);
+            component.on_init();
+        }
+
+        let children = subtree.children.into_iter()
+            .map(|child| self.insert_node(Some(id), child, corner, opacity))
+            .collect();
+        self.nodes.insert(id, SceneNode{
+            component,
+            local_position: subtree.position,
+            local_opacity: subtree.opacity,
+            z_index: subtree.z_index,
+            size: subtree.size,
+            floating: subtree.floating,
+            parent,
+            children
+        });
+        id
+    }
+
+    // Takes a component and everything under it out of the scene
+    pub fn remove_subtree(&mut self, id: ComponentId) -> Option<Subtree>{
+        let parent = self.nodes.get(&id)?.parent;
+        let siblings = match parent{
+            Some(parent) => &mut self.nodes.get_mut(&parent).unwrap().children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| *sibling != id);
+        if self.focused.is_some_and(|focused| focused == id || self.ancestors(focused).any(|a| a == id)){
+            self.focused = None;
+        }
+        self.dirty = true;
+        Some(self.remove_node(id))
+    }
+
+    fn remove_node(&mut self, id: ComponentId) -> Subtree{
+        let node = self.nodes.remove(&id).unwrap();
+        Subtree{
+            component: node.component,
+            position: node.local_position,
+            opacity: node.local_opacity,
+            z_index: node.z_index,
+            size: node.size,
+            floating: node.floating,
+            children: node.children.into_iter().map(|child| self.remove_node(child)).collect()
+        }
+    }
+
+    pub fn contains(&self, id: ComponentId) -> bool{
+        self.nodes.contains_key(&id)
+    }
+
+    pub fn get(&self, id: ComponentId) -> Option<&dyn ComponentObject>{
+        self.nodes.get(&id)?.component.as_deref()
+    }
+
+    pub fn get_mut(&mut self, id: ComponentId) -> Option<&mut dyn ComponentObject>{
+        match &mut self.nodes.get_mut(&id)?.component{
+            Some(component) => Some(component.as_mut()),
+            None => None,
+        }
     }
 
-    pub fn get_components(&self) -> &[Component]{
-        self.components.as_slice()
+    pub fn parent(&self, id: ComponentId) -> Option<ComponentId>{
+        self.nodes.get(&id)?.parent
+    }
+
+    // Empty if id isn't in the scene
+    pub fn children(&self, id: ComponentId) -> &[ComponentId]{
+        self.nodes.get(&id).map_or(&[], |node| node.children.as_slice())
+    }
+
+    pub fn roots(&self) -> &[ComponentId]{
+        &self.roots
+    }
+
+    pub fn ancestors(&self, id: ComponentId) -> Ancestors<'_>{
+        Ancestors{
+            scene: self,
+            next: self.parent(id)
+        }
+    }
+
+    // Every component in the scene
+    pub fn depth_first(&self) -> DepthFirst<'_>{
+        DepthFirst{
+            scene: self,
+            stack: self.roots.iter().rev().copied().collect()
+        }
+    }
+
+    // id and everything under it
+    pub fn depth_first_from(&self, id: ComponentId) -> DepthFirst<'_>{
+        DepthFirst{
+            scene: self,
+            stack: if self.contains(id) { vec![id] } else { Vec::new() }
+        }
+    }
+
+    pub fn local_position(&self, id: ComponentId) -> Option<[f32; 2]>{
+        Some(self.nodes.get(&id)?.local_position)
+    }
+
+    // Where the top left corner ends up on screen, after scrolling
+    pub fn absolute_position(&self, id: ComponentId) -> Option<[f32; 2]>{
+        let mut corner = self.local_position(id)?;
+        for ancestor in self.ancestors(id){
+            let offset = self.nodes[&ancestor].local_position;
+            corner = [corner[0] + offset[0], corner[1] + offset[1]];
+        }
+        Some([corner[0] - self.scroll[0], corner[1] - self.scroll[1]])
+    }
+
+    // Moves id relative to its parent, taking everything under it along
+    pub fn set_local_position(&mut self, id: ComponentId, position: [f32; 2]){
+        let Some(node) = self.nodes.get_mut(&id) else { return };
+        node.local_position = position;
+        self.place(id);
+    }
+
+    // Moves the components in id's subtree to where they belong on screen
+    fn place(&mut self, id: ComponentId){
+        self.dirty = true;
+        let ids: Vec<ComponentId> = self.depth_first_from(id).collect();
+        for id in ids{
+            let corner = self.absolute_position(id).unwrap();
+            if let Some(component) = self.get_mut(id){
+                component.set_position(corner);
+            }
+        }
+    }
+
+    // How solid id is drawn, its own opacity times every ancestor's
+    // This is the alpha id's component is drawn with by itself, the subtree
+    // isn't flattened first, so faded children that overlap blend with each other
+    pub fn opacity(&self, id: ComponentId) -> Option<f32>{
+        let own = self.nodes.get(&id)?.local_opacity;
+        Some(self.ancestors(id).fold(own, |opacity, ancestor| opacity * self.nodes[&ancestor].local_opacity))
+    }
+
+    // Fades id and everything under it, ie for fading something in or out
+    // Every component in the subtree is faded on its own, see opacity
+    pub fn set_local_opacity(&mut self, id: ComponentId, opacity: f32){
+        let Some(node) = self.nodes.get_mut(&id) else { return };
+        node.local_opacity = opacity;
+        self.dirty = true;
+        let ids: Vec<ComponentId> = self.depth_first_from(id).collect();
+        for id in ids{
+            let opacity = self.opacity(id).unwrap();
+            if let Some(component) = self.get_mut(id){
+                component.set_opacity(opacity);
+            }
+        }
+    }
+
+    // The layer id is drawn on, its own z-index or else the
+    // closest ancestor's that has one, 0 if none of them do
+    pub fn layer(&self, id: ComponentId) -> Option<i32>{
+        let own = self.nodes.get(&id)?.z_index;
+        Some(own
+            .or_else(|| self.ancestors(id).find_map(|ancestor| self.nodes[&ancestor].z_index))
+            .unwrap_or(0))
+    }
+
+    // Moves id and everything on its layer under it to another layer,
+    // or back to its parent's with None
+    pub fn set_z_index(&mut self, id: ComponentId, z_index: Option<i32>){
+        let Some(node) = self.nodes.get_mut(&id) else { return };
+        node.z_index = z_index;
+        self.dirty = true;
+    }
+
+    // Everything in the scene from the bottom up, lower layers first
+    // and in depth first order within a layer
+    pub fn draw_order(&self) -> Vec<ComponentId>{
+        self.layered_draw_order().into_iter().map(|(_, id)| id).collect()
+    }
+
+    // draw_order along with the layer each one is on
+    fn layered_draw_order(&self) -> Vec<(i32, ComponentId)>{
+        // parents are always walked before their children,
+        // so their layer is already known when a child needs it
+        let mut layers: HashMap<ComponentId, i32> = HashMap::with_capacity(self.nodes.len());
+        let mut order: Vec<(i32, ComponentId)> = Vec::with_capacity(self.nodes.len());
+        for id in self.depth_first(){
+            let node = &self.nodes[&id];
+            let layer = node.z_index
+                .or_else(|| node.parent.map(|parent| layers[&parent]))
+                .unwrap_or(0);
+            layers.insert(id, layer);
+            order.push((layer, id));
+        }
+        // stable, so the depth first order is kept within a layer
+        order.sort_by_key(|(layer, _)| *layer);
+        order
+    }
+
+    // The component drawn on top at point, if any
+    pub fn component_at(&self, point: [f32; 2]) -> Option<ComponentId>{
+        self.draw_order()
+            .into_iter()
+            .rev()
+            .find(|id| self.get(*id).is_some_and(|component| component.contains(point)))
+    }
+
+    pub fn scroll_position(&self) -> [f32; 2]{
+        self.scroll
+    }
+
+    // Puts this point of the document at the top left of the screen
+    pub fn scroll_to(&mut self, position: [f32; 2]){
+        self.scroll = position;
+        for root in self.roots.clone(){
+            self.place(root);
+        }
+    }
+
+    pub fn focused(&self) -> Option<ComponentId>{
+        self.focused
+    }
+
+    // Ignored if id isn't in the scene
+    pub fn focus(&mut self, id: Option<ComponentId>){
+        if id.is_none_or(|id| self.contains(id)){
+            self.focused = id;
+        }
+    }
+
+    // For making text components that go in this scene
+    pub fn glyph_atlas(&self) -> Rc<RefCell<GlyphAtlas>>{
+        Rc::clone(&self.glyph_atlas)
+    }
+
+    // Whether the scene needs drawing again
+    // Nothing is redrawn while this is false, so a still document costs nothing
+    pub fn is_dirty(&self) -> bool{
+        self.dirty
+    }
+
+    // For anything that changes how the scene looks from outside,
+    // ie the window being resized
+    pub fn mark_dirty(&mut self){
+        self.dirty = true;
+    }
+
+    // Gets every component ready to be drawn on backend
+    // and uploads anything they added to the glyph atlas
+    // Textures nothing uses anymore, like a removed component's, are freed here too
+    // The scene counts as drawn after this
+    pub fn pre_render<B: RenderBackend>(&mut self, backend: &mut B){
+        self.dirty = false;
+        self.glyph_atlas.borrow_mut().begin_frame();
+        let ids: Vec<ComponentId> = self.depth_first().collect();
+        for id in ids{
+            if let Some(comp) = self.get_mut(id){
+                comp.pre_render();
+            }
+        }
+        self.glyph_atlas.borrow_mut().upload(backend);
+        backend.free_unused_textures();
+    }
+
+    // In draw order with the layer each is on, see draw_order
+    pub fn get_components(&self) -> Vec<(i32, &dyn ComponentObject)>{
+        self.layered_draw_order()
+            .into_iter()
+            .filter_map(|(layer, id)| Some((layer, self.get(id)?)))
+            .collect()
     }
 
     // fun parts
-    pub fn update(&mut self, input: &InputHandler){
-        for comp in &mut self.components{
+    // Returns what the components asked for, in the order they asked
+    // Commands about "self" are turned into ones naming the component
+    pub fn update(&mut self, input: &InputHandler) -> Vec<UpdateCommand>{
+        // Only the topmost component under the mouse is hovered,
+        // along with everything it's inside
+        let hovered: HashSet<ComponentId> = match self.component_at(input.get_mouse_pos()){
+            Some(top) => std::iter::once(top).chain(self.ancestors(top)).collect(),
+            None => HashSet::new(),
+        };
+
+        let mut commands = Vec::new();
+        let ids: Vec<ComponentId> = self.depth_first().collect();
+        for id in ids{
+            let Some(comp) = self.get_mut(id) else { continue };
+            comp.set_hovered(hovered.contains(&id));
             let command = comp.update(input);
+            let changed = comp.take_changed();
+            self.dirty |= changed;
+            match command{
+                UpdateCommand::Void => {},
+                UpdateCommand::FocusSelf => commands.push(UpdateCommand::Focus(id)),
+                UpdateCommand::RemoveSelf => commands.push(UpdateCommand::Remove(id)),
+                command => commands.push(command),
+            }
         }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use winit::{dpi::PhysicalPosition, event::{DeviceId, ModifiersState, WindowEvent}};
+
+    use super::*;
+    use crate::{file_reader, render::software::SoftwareRenderer};
+
+    fn scene(markup: &str) -> SceneState{
+        let document = file_reader::read_file(markup).unwrap();
+        let mut backend = SoftwareRenderer::new(200, 200);
+        SceneState::from_document(&document, &mut backend)
+    }
+
+    fn mouse_at([x, y]: [f32; 2]) -> InputHandler{
+        let mut input = InputHandler::new_default();
+        #[allow(deprecated)]
+        input.handle_window_event(&WindowEvent::CursorMoved{
+            // never looked at
+            device_id: unsafe { DeviceId::dummy() },
+            position: PhysicalPosition::new(x as f64, y as f64),
+            modifiers: ModifiersState::empty()
+        });
+        input
+    }
+
+    fn color_of(scene: &SceneState, id: ComponentId) -> Color{
+        scene.get(id).unwrap().get_instances()[0].color
+    }
+
+    #[test]
+    fn hover_shows_its_hover_color_under_the_mouse(){
+        let mut scene = scene(
+            r#"<hover definition="x:10; y:10; width:50; height:50; c:(255,0,0); hover-c:(0,0,255);"/>"#
+        );
+        let hover = scene.roots()[0];
+        let base = Color::new([1.0, 0.0, 0.0, 1.0]);
+        let hovered = Color::new([0.0, 0.0, 1.0, 1.0]);
+
+        scene.update(&mouse_at([100.0, 100.0]));
+        assert_eq!(color_of(&scene, hover), base);
+
+        scene.pre_render(&mut SoftwareRenderer::new(200, 200));
+        scene.update(&mouse_at([30.0, 30.0]));
+        assert_eq!(color_of(&scene, hover), hovered);
+        assert!(scene.is_dirty());
+
+        scene.update(&mouse_at([100.0, 100.0]));
+        assert_eq!(color_of(&scene, hover), base);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn the_topmost_layer_wins(){
+        let mut scene = scene(r#"<main>
+            <square definition="x:0; y:0; width:50; height:50; c:(255,0,0); z:2;"/>
+            <square definition="x:10; y:10; width:50; height:50; c:(0,255,0);"/>
+            <hover definition="x:20; y:20; width:50; height:50; c:(0,0,255); hover-c:(255,255,255); z:-1;">
+                <square definition="x:0; y:0; width:10; height:10; c:(0,0,0);"/>
+            </hover>
+        </main>"#);
+        let main = scene.roots()[0];
+        let &[top, middle, bottom] = scene.children(main) else { panic!("expected 3 children") };
+        let bottom_child = scene.children(bottom)[0];
+
+        assert_eq!(scene.draw_order(), vec![bottom, bottom_child, main, middle, top]);
+        // later in the file, but on a lower layer
+        assert_eq!(scene.component_at([15.0, 15.0]), Some(top));
+        assert_eq!(scene.component_at([55.0, 55.0]), Some(middle));
+        assert_eq!(scene.component_at([65.0, 65.0]), Some(bottom));
+        assert_eq!(scene.component_at([100.0, 100.0]), None);
+
+        // only hovered where nothing on a higher layer covers it
+        let blue = Color::new([0.0, 0.0, 1.0, 1.0]);
+        scene.update(&mouse_at([55.0, 55.0]));
+        assert_eq!(color_of(&scene, bottom), blue);
+        scene.update(&mouse_at([65.0, 65.0]));
+        assert_eq!(color_of(&scene, bottom), Color::WHITE);
+    }
+
+    #[test]
+    fn document_order_within_a_layer(){
+        let scene = scene(r#"<main>
+            <square definition="x:0; y:0; width:50; height:50; c:(255,0,0);">
+                <square definition="x:0; y:0; width:40; height:40; c:(0,255,0);"/>
+            </square>
+            <square definition="x:20; y:20; width:50; height:50; c:(0,0,255);"/>
+        </main>"#);
+        let main = scene.roots()[0];
+        let &[first, second] = scene.children(main) else { panic!("expected 2 children") };
+        let first_child = scene.children(first)[0];
+
+        assert_eq!(scene.draw_order(), vec![main, first, first_child, second]);
+        // children go over their parent
+        assert_eq!(scene.component_at([10.0, 10.0]), Some(first_child));
+        assert_eq!(scene.component_at([45.0, 5.0]), Some(first));
+        // and everything in an earlier sibling goes under a later one
+        assert_eq!(scene.component_at([30.0, 30.0]), Some(second));
+    }
+
+    #[test]
+    fn children_take_their_parents_layer(){
+        let mut scene = scene(r#"<main>
+            <square definition="x:0; y:0; width:50; height:50; z:3;">
+                <square definition="x:0; y:0; width:10; height:10;">
+                    <square definition="x:0; y:0; width:5; height:5;"/>
+                </square>
+                <square definition="x:20; y:0; width:10; height:10; z:-2;"/>
+            </square>
+            <square definition="x:0; y:0; width:100; height:100; z:1;"/>
+        </main>"#);
+        let main = scene.roots()[0];
+        let &[parent, cover] = scene.children(main) else { panic!("expected 2 children") };
+        let &[inherits, own] = scene.children(parent) else { panic!("expected 2 children") };
+        let grandchild = scene.children(inherits)[0];
+
+        assert_eq!(scene.layer(main), Some(0));
+        assert_eq!(scene.layer(inherits), Some(3));
+        assert_eq!(scene.layer(grandchild), Some(3));
+        assert_eq!(scene.layer(own), Some(-2));
+        assert_eq!(scene.draw_order(), vec![own, main, cover, parent, inherits, grandchild]);
+        assert_eq!(scene.component_at([2.0, 2.0]), Some(grandchild));
+        // own is on a lower layer than the parent it sits inside
+        assert_eq!(scene.component_at([25.0, 5.0]), Some(parent));
+
+        scene.set_z_index(parent, None);
+        assert_eq!(scene.layer(grandchild), Some(0));
+        assert_eq!(scene.component_at([2.0, 2.0]), Some(cover));
+    }
+
+    fn rect_of(scene: &SceneState, id: ComponentId) -> [[f32; 2]; 2]{
+        scene.get(id).unwrap().get_instances()[0].rect
+    }
+
+    // Where the lowest glyph of some text ends
+    fn text_bottom(scene: &mut SceneState, id: ComponentId) -> f32{
+        scene.pre_render(&mut SoftwareRenderer::new(200, 200));
+        scene.get(id).unwrap().get_instances().iter()
+            .map(|instance| instance.rect[1][1])
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn float_tags_follow_the_screen_size(){
+        let mut scene = scene(r#"<main>
+            <square geometry=FLOAT definition="c:(255,0,0);">
+                <square definition="x:10; y:10; width:20; height:20;"/>
+                <square geometry=FLOAT/>
+            </square>
+            <square definition="width:50; height:50;">
+                <square geometry=FLOAT/>
+            </square>
+            <text geometry=FLOAT definition="size:20;">one two three four five six seven</text>
+        </main>"#);
+        let main = scene.roots()[0];
+        let &[background, fixed, text] = scene.children(main) else { panic!("expected 3 children") };
+        let &[custom, inner] = scene.children(background) else { panic!("expected 2 children") };
+        let fixed_child = scene.children(fixed)[0];
+        assert_eq!(rect_of(&scene, background), [[0.0, 0.0], [200.0, 200.0]]);
+
+        scene.relayout([300.0, 100.0]);
+        assert!(scene.is_dirty());
+        assert_eq!(rect_of(&scene, background), [[0.0, 0.0], [300.0, 100.0]]);
+        assert_eq!(rect_of(&scene, inner), [[0.0, 0.0], [300.0, 100.0]]);
+        // only FLOAT tags change, and they fill what they're inside
+        assert_eq!(rect_of(&scene, custom), [[10.0, 10.0], [30.0, 30.0]]);
+        assert_eq!(rect_of(&scene, fixed_child), [[0.0, 0.0], [50.0, 50.0]]);
+
+        // text wraps again to the new width
+        let wide = text_bottom(&mut scene, text);
+        scene.relayout([80.0, 400.0]);
+        assert!(text_bottom(&mut scene, text) > wide);
+    }
+
+    fn square(corner: [f32; 2]) -> SquareComponent{
+        SquareComponent::new(corner, 10.0, 10.0, Color::WHITE)
+    }
+
+    #[test]
+    fn ids_stay_the_same_when_a_sibling_is_removed(){
+        let mut scene = SceneState::new_empty();
+        let first = scene.add_component(square([0.0, 0.0]));
+        let second = scene.add_component(square([10.0, 0.0]));
+        let child = scene.add_child(second, square([1.0, 1.0])).unwrap();
+        let third = scene.add_component(square([20.0, 0.0]));
+
+        scene.remove_subtree(first).unwrap();
+        assert!(!scene.contains(first));
+        assert_eq!(scene.roots(), &[second, third]);
+        assert_eq!(scene.children(second), &[child]);
+        assert_eq!(scene.get(third).unwrap().position(), [20.0, 0.0]);
+        // a removed id is never handed out again
+        let fourth = scene.add_component(square([0.0, 0.0]));
+        assert!(![first, second, child, third].contains(&fourth));
+    }
+
+    #[test]
+    fn children_are_placed_relative_to_their_parent(){
+        let mut scene = SceneState::new_empty();
+        let parent = scene.add_component(square([100.0, 50.0]));
+        let child = scene.add_child(parent, square([10.0, 20.0])).unwrap();
+        let grandchild = scene.add_child(child, square([1.0, 2.0])).unwrap();
+
+        assert_eq!(scene.local_position(grandchild), Some([1.0, 2.0]));
+        assert_eq!(scene.absolute_position(grandchild), Some([111.0, 72.0]));
+        assert_eq!(scene.get(grandchild).unwrap().position(), [111.0, 72.0]);
+        assert_eq!(scene.ancestors(grandchild).collect::<Vec<_>>(), vec![child, parent]);
+
+        scene.set_local_position(parent, [0.0, 0.0]);
+        assert_eq!(scene.get(grandchild).unwrap().position(), [11.0, 22.0]);
+
+        scene.scroll_to([5.0, 10.0]);
+        assert_eq!(scene.absolute_position(grandchild), Some([6.0, 12.0]));
+        assert_eq!(scene.get(grandchild).unwrap().position(), [6.0, 12.0]);
+        // added while scrolled, still relative to the document
+        let late = scene.add_child(child, square([0.0, 0.0])).unwrap();
+        assert_eq!(scene.get(late).unwrap().position(), [5.0, 10.0]);
+    }
+
+    #[test]
+    fn removed_subtrees_can_go_back_in(){
+        let mut scene = SceneState::new_empty();
+        let left = scene.add_component(square([0.0, 0.0]));
+        let right = scene.add_component(square([100.0, 0.0]));
+        let moved = scene.add_child(left, square([5.0, 5.0])).unwrap();
+        scene.add_child(moved, square([1.0, 1.0])).unwrap();
+        scene.focus(Some(moved));
+
+        let subtree = scene.remove_subtree(moved).unwrap();
+        assert_eq!(scene.focused(), None);
+        assert!(scene.children(left).is_empty());
+        assert_eq!(subtree.children.len(), 1);
+
+        let moved = scene.insert_subtree(Some(right), subtree).unwrap();
+        assert_eq!(scene.children(right), &[moved]);
+        let grandchild = scene.children(moved)[0];
+        // keeps its offset from the new parent
+        assert_eq!(scene.get(moved).unwrap().position(), [105.0, 5.0]);
+        assert_eq!(scene.get(grandchild).unwrap().position(), [106.0, 6.0]);
+        assert_eq!(scene.depth_first().count(), 4);
+    }
+}
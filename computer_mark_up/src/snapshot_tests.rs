@@ -0,0 +1,186 @@
+/*
+ * Golden image tests
+ * Every .cmu file under TestFiles/ is rendered with the software renderer
+ * and compared against TestFiles/snapshots/<name>.png
+ *
+ * CMU_BLESS_SNAPSHOTS=1        write the current output as the new reference
+ * CMU_SNAPSHOT_TOLERANCE=<n>   how far any channel of a pixel can be off, default 0
+ *
+ * When a file doesn't match, the actual render and an image with the
+ * differing pixels in red are written to target/snapshot_diffs/
+ *
+ * The files are also drawn with the gpu, which has to come out the same
+ * as the software renderer for the snapshots to mean anything for it
+ */
+
+use std::{env, fmt, fs, path::{Path, PathBuf}};
+
+use crate::{
+    file_reader,
+    input_handler::InputHandler,
+    render::{backend::RenderBackend, headless::{HeadlessError, HeadlessState}, software::SoftwareRenderer},
+    scene_state::SceneState
+};
+
+const SNAPSHOT_SIZE: [u32; 2] = [800, 600];
+
+// Blending rounds a little differently on the gpu
+const GPU_TOLERANCE: u8 = 2;
+
+fn test_files_dir() -> PathBuf{
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../TestFiles")
+}
+
+fn diff_dir() -> PathBuf{
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target/snapshot_diffs")
+}
+
+fn find_cmu_files(dir: &Path, found: &mut Vec<PathBuf>){
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    // sorted so failures always come out in the same order
+    entries.sort();
+    for path in entries{
+        if path.is_dir(){
+            find_cmu_files(&path, found);
+        } else if path.extension().is_some_and(|e| e == "cmu"){
+            found.push(path);
+        }
+    }
+}
+
+fn render_file<B>(path: &Path, backend: &mut B) -> image::RgbaImage
+where
+    B: RenderBackend<Output = image::RgbaImage>,
+    B::Error: fmt::Display
+{
+    let document = match file_reader::read_path(path.to_str().unwrap()){
+        Ok(document) => document,
+        Err(e) => panic!("{}", e),
+    };
+    let mut scene = SceneState::from_document(&document, backend);
+    scene.update(&InputHandler::new_default());
+    scene.pre_render(backend);
+    match backend.render(&scene.get_components()){
+        Ok(image) => image,
+        Err(e) => panic!("couldn't render {}: {}", path.display(), e),
+    }
+}
+
+fn render_software(path: &Path) -> image::RgbaImage{
+    render_file(path, &mut SoftwareRenderer::new(SNAPSHOT_SIZE[0], SNAPSHOT_SIZE[1]))
+}
+
+// Every file in TestFiles/ and the name its snapshot goes by
+fn test_files() -> Vec<(PathBuf, String)>{
+    let test_files = test_files_dir();
+    let mut files = Vec::new();
+    find_cmu_files(&test_files, &mut files);
+    assert!(!files.is_empty(), "no .cmu files found in {}", test_files.display());
+    files.into_iter().map(|file| {
+        let name = file.strip_prefix(&test_files).unwrap().with_extension("");
+        let name = name.to_string_lossy().replace(['/', '\\'], "_");
+        (file, name)
+    }).collect()
+}
+
+// Returns an image with every pixel that is off by more than
+// tolerance in red, or None if they all match
+fn compare(expected: &image::RgbaImage, actual: &image::RgbaImage, tolerance: u8) -> Option<image::RgbaImage>{
+    if expected.dimensions() != actual.dimensions(){
+        return Some(actual.clone());
+    }
+    let mut diff = image::RgbaImage::new(expected.width(), expected.height());
+    let mut differs = false;
+    for (x, y, expected_pixel) in expected.enumerate_pixels(){
+        let actual_pixel = actual.get_pixel(x, y);
+        let off = expected_pixel.0.iter()
+            .zip(actual_pixel.0.iter())
+            .any(|(e, a)| e.abs_diff(*a) > tolerance);
+        let diff_pixel = if off{
+            differs = true;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            // faded out version of what was expected, so the red stands out
+            let [r, g, b, _] = expected_pixel.0;
+            let grey = ((r as u16 + g as u16 + b as u16) / 3 / 4) as u8;
+            image::Rgba([grey, grey, grey, 255])
+        };
+        diff.put_pixel(x, y, diff_pixel);
+    }
+    differs.then_some(diff)
+}
+
+#[test]
+fn cmu_files_match_snapshots(){
+    let bless = env::var("CMU_BLESS_SNAPSHOTS").is_ok_and(|v| v == "1");
+    let tolerance = env::var("CMU_SNAPSHOT_TOLERANCE")
+        .map(|v| v.parse::<u8>().expect("CMU_SNAPSHOT_TOLERANCE needs to be between 0 and 255"))
+        .unwrap_or(0);
+
+    let snapshots = test_files_dir().join("snapshots");
+    let mut failures = Vec::new();
+    for (file, name) in test_files(){
+        let reference = snapshots.join(format!("{}.png", name));
+        let actual = render_software(&file);
+
+        if bless{
+            fs::create_dir_all(&snapshots).unwrap();
+            actual.save(&reference).unwrap();
+            continue;
+        }
+
+        let expected = match image::open(&reference){
+            Ok(expected) => expected.to_rgba8(),
+            Err(_) => {
+                failures.push(format!(
+                    "{}: no snapshot at {}, run with CMU_BLESS_SNAPSHOTS=1 to create it",
+                    name, reference.display()
+                ));
+                continue;
+            }
+        };
+
+        if let Some(diff) = compare(&expected, &actual, tolerance){
+            let diffs = diff_dir();
+            fs::create_dir_all(&diffs).unwrap();
+            actual.save(diffs.join(format!("{}.actual.png", name))).unwrap();
+            diff.save(diffs.join(format!("{}.diff.png", name))).unwrap();
+            failures.push(format!(
+                "{}: doesn't match {}, see {}",
+                name, reference.display(), diffs.display()
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "snapshot mismatches:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn gpu_matches_software(){
+    let mut gpu = match pollster::block_on(HeadlessState::new(SNAPSHOT_SIZE[0], SNAPSHOT_SIZE[1])){
+        Ok(gpu) => gpu,
+        Err(HeadlessError::NoAdapter) => {
+            eprintln!("no graphics adapter, skipping the gpu comparison");
+            return;
+        },
+        Err(e) => panic!("{}", e),
+    };
+
+    let mut failures = Vec::new();
+    for (file, name) in test_files(){
+        let software = render_software(&file);
+        let actual = render_file(&file, &mut gpu);
+        if let Some(diff) = compare(&software, &actual, GPU_TOLERANCE){
+            let diffs = diff_dir();
+            fs::create_dir_all(&diffs).unwrap();
+            actual.save(diffs.join(format!("{}.gpu.png", name))).unwrap();
+            diff.save(diffs.join(format!("{}.gpu.diff.png", name))).unwrap();
+            failures.push(format!("{}: the gpu draws it differently, see {}", name, diffs.display()));
+        }
+    }
+
+    assert!(failures.is_empty(), "gpu mismatches:\n{}", failures.join("\n"));
+}
@@ -1,10 +1,39 @@
+use winit::window::CursorIcon;
+
+use crate::scene_state::{ComponentId, Subtree};
+
 /*
- * These are returned by Component.update() and 
+ * These are returned by Component.update() and
  * give information about what the window should do
  * afterwards
+ * SceneState.update() collects them and main carries them out,
+ * so components never need to touch the window themselves
  */
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+// The built in components only use a few of these
+#[derive(Debug)]
 pub enum UpdateCommand{
     Void, // don't do anything
-}
\ No newline at end of file
+    Quit,
+    SetWindowTitle(String),
+    // Draw again even if nothing looks like it changed
+    // Components keep being updated every frame for as long
+    // as they return this, ie while something is animating
+    RequestRedraw,
+    // Replace the document with another .cmu file
+    // relative paths start from the current file's folder
+    Navigate(String),
+    // Scroll so this point of the document is at the top left of the window
+    ScrollTo([f32; 2]),
+    Focus(ComponentId),
+    // Focus whichever component returned this
+    FocusSelf,
+    // Take focus away from everything
+    Unfocus,
+    // Add a subtree under parent, or at the top level if it's None
+    Spawn{ parent: Option<ComponentId>, subtree: Subtree },
+    Remove(ComponentId),
+    // Remove whichever component returned this, and everything under it
+    RemoveSelf,
+    SetCursorIcon(CursorIcon),
+}